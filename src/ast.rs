@@ -1,32 +1,59 @@
+use crate::lexer::NumLit;
+
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Number(f64),
+    Number(NumLit),
     Str(String),
     Ident(String),
     Input(Vec<Expr>), 
-    Call(String, Vec<Expr>),
-    Binary(Box<Expr>, String, Box<Expr>),
+    Call(String, Vec<Expr>, usize),          // 最后一项为调用点编号（源码位置）
+    Binary(Box<Expr>, String, Box<Expr>, usize),
     Logical(String, Box<Expr>, Box<Expr>),
     Not(Box<Expr>),
     Array(Vec<Expr>),
-    Index(Box<Expr>, Box<Expr>),
+    Index(Box<Expr>, Box<Expr>, usize),
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>, usize), // s[1:3], s[:2], s[1:]
     Object(Vec<(String, Box<Expr>)>),
-    Access(Box<Expr>, String),
+    Access(Box<Expr>, String, usize),
     PostfixIncrement(String),
     PostfixDecrement(String),
+    SafeAccess(Box<Expr>, String, usize),  // a?.b
+    SafeMethodCall {                // a?.>f(args)
+        target: Box<Expr>,
+        name: String,
+        args: Vec<Expr>,
+        site: usize,
+    },
+    Coalesce(Box<Expr>, Box<Expr>), // a ?? b
     MethodCall {
         target: Box<Expr>,      // a 或更长链
         name: String,           // func
         args: Vec<Expr>,        // 附加实参（不含 target）
+        site: usize,            // 调用点编号，用于分派缓存
+    },
+    Construct(String, Vec<(String, Box<Expr>)>), // Point{x:1, y:2}
+    Variant(String, String, Vec<Expr>),          // Shape.Circle(2)
+    Is(Box<Expr>, String, String, Vec<String>),  // s is Shape.Circle(r)
+    Range {                                      // a..b, a..=b, a..b:step
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+        step: Option<Box<Expr>>,
+        site: usize,
     },
 }
 
 #[derive(Debug, Clone)]
 pub enum LoopKind {
-    Times(Expr),                         // L>[10]
-    ForEach(String, Expr),               // L>data:item
+    Times(Expr, Option<String>),         // L>[10] / L>[10]:i
+    ForEach(Option<String>, String, Expr), // L>data:item / L>data:(i, item)
+    ParallelForEach(Option<String>, String, Expr), // L>||data:item
     While(Expr),                         // L>(condition)
     For(Box<Stmt>, Expr, Box<Stmt>),     // L>(init; cond; step)
+    Infinite,                            // L>{ ... }
+    DoWhile(Expr),                       // L>{ ... }(condition)
+    Until(Expr),                         // L>!(condition){ ... }
+    DoUntil(Expr),                       // L>{ ... }!(condition)
 }
 
 #[derive(Debug, Clone)]
@@ -36,17 +63,55 @@ pub enum Stmt {
     Loop(LoopKind, Vec<Stmt>),
     MultiIf(Vec<(Option<Expr>, Vec<Stmt>)>),
     Assign(String, Expr),
+    ForceAssign(String, Option<String>, Expr), // x(int) =:: "42"
+    Bind(String, Expr),          // total => price * qty
+    BindTwo(String, String),     // a <=> b
     Increment(String),
     Decrement(String),
     PropAssign(Box<Expr>, Expr),
     #[allow(dead_code)]
     Expr(Expr),
     Return(Expr),
+    Break,
+    Yield(Expr),                 // Y>value
 }
 
 #[derive(Debug)]
 pub struct Function {
     pub name: String,
+    pub owner: Option<String>,  // F>Point.norm 的 Point
     pub params: Vec<(String, Option<String>)>,
     pub body: Vec<Stmt>,
-}
\ No newline at end of file
+    pub doc: Option<String>,    // 紧挨在 F> 前的 `///` 注释
+}
+
+// T>Point{ x(float), y(float) }
+#[derive(Debug)]
+pub struct TypeDecl {
+    pub name: String,
+    pub interfaces: Vec<String>,   // T>Stack: Iterable{...}
+    pub fields: Vec<(String, Option<String>)>,
+}
+
+// E>Shape{ Circle(r), Rect(w, h), Empty }
+#[derive(Debug)]
+pub struct EnumDecl {
+    pub name: String,
+    pub interfaces: Vec<String>,
+    pub variants: Vec<(String, Vec<String>)>,
+}
+
+// P>Iterable{ length, get(i) }  —— 方法名与参数个数（不含接收者）
+#[derive(Debug)]
+pub struct InterfaceDecl {
+    pub name: String,
+    pub methods: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Program {
+    pub types: Vec<TypeDecl>,
+    pub enums: Vec<EnumDecl>,
+    pub interfaces: Vec<InterfaceDecl>,
+    pub functions: Vec<Function>,
+}
@@ -1,10 +1,28 @@
+use crate::lexer::LANGUAGES;
+
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub strict: bool,
+    pub mock_clock: bool,
+    pub max_depth: Option<usize>,
+    pub lang: Option<String>,     // 诊断信息的语言
+    pub keywords: Option<String>, // 本地化关键字
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
 pub enum CliAction {
     ShowHelp,
     ShowVersion,
-    RunFile(String),
-    ShowTokens(String),
-    ShowAst(String),
-    SyntaxCheck(String),
+    RunFile(String, RunOptions),
+    ShowTokens(String, RunOptions),
+    ShowAst(String, RunOptions),
+    SyntaxCheck(String, RunOptions),
+    ShowDocs(String, DocFormat, RunOptions),
     Invalid(String),
 }
 
@@ -16,11 +34,52 @@ pub fn parse_args(args: &[String]) -> CliAction {
     match args[1].as_str() {
         "-v" | "--version" => CliAction::ShowVersion,
         "-h" | "--help" => CliAction::ShowHelp,
-        "--token" if args.len() > 2 => CliAction::ShowTokens(args[2].clone()),
-        "--ast"   if args.len() > 2 => CliAction::ShowAst(args[2].clone()),
-        "--check" if args.len() > 2 => CliAction::SyntaxCheck(args[2].clone()),
-        s if s.ends_with(".fx")     => CliAction::RunFile(s.to_string()),
-        other => CliAction::Invalid(other.to_string()),
+        _ => parse_run_args(&args[1..]),
+    }
+}
+
+// [options] <file.fx>, [options] --token|--ast|--check <file>, or doc [--html] <file>
+fn parse_run_args(args: &[String]) -> CliAction {
+    let mut options = RunOptions::default();
+    let mut mode = None;
+    let mut format = DocFormat::Markdown;
+    let mut file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // 模式标志只选择模式，文件仍由下面的 *.fx 分支接收，选项可以出现在任意位置
+            "--token" | "--ast" | "--check" if mode.is_none() => mode = Some(arg.as_str()),
+            "doc" if mode.is_none() && file.is_none() => mode = Some("doc"),
+            "--html" if mode == Some("doc") => format = DocFormat::Html,
+            "--strict" => options.strict = true,
+            "--lang" => match args.next() {
+                Some(l) if LANGUAGES.contains(&l.as_str()) => options.lang = Some(l.clone()),
+                _ => return CliAction::Invalid("--lang".to_string()),
+            },
+            "--keywords" => match args.next() {
+                Some(l) if LANGUAGES.contains(&l.as_str()) => options.keywords = Some(l.clone()),
+                _ => return CliAction::Invalid("--keywords".to_string()),
+            },
+            "--mock-clock" => options.mock_clock = true,
+            "--max-depth" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => options.max_depth = Some(n),
+                None => return CliAction::Invalid("--max-depth".to_string()),
+            },
+            s if s.ends_with(".fx") && file.is_none() => file = Some(s.to_string()),
+            other => return CliAction::Invalid(other.to_string()),
+        }
+    }
+    // --lang zh 同时启用中文关键字，除非 --keywords 另有指定
+    if options.keywords.is_none() {
+        options.keywords = options.lang.clone();
+    }
+    match (mode, file) {
+        (_, None) => CliAction::ShowHelp,
+        (Some("--token"), Some(f)) => CliAction::ShowTokens(f, options),
+        (Some("--ast"), Some(f)) => CliAction::ShowAst(f, options),
+        (Some("doc"), Some(f)) => CliAction::ShowDocs(f, format, options),
+        (Some(_), Some(f)) => CliAction::SyntaxCheck(f, options),
+        (None, Some(f)) => CliAction::RunFile(f, options),
     }
 }
 
@@ -28,6 +87,7 @@ pub fn show_help() {
     println!("FLYUX - Ultra minimal language runtime");
     println!("Usage:");
     println!("  flyux [options] <file.fx>");
+    println!("  flyux doc [--html] <file.fx>");
     println!();
     println!("Options:");
     println!("  -v, --version       Show version");
@@ -35,4 +95,14 @@ pub fn show_help() {
     println!("  --token <file.fx>   Print token stream");
     println!("  --ast <file.fx>     Print abstract syntax tree");
     println!("  --check <file.fx>   Check syntax only");
+    println!("  doc <file.fx>       Print F> signatures and /// docs as Markdown");
+    println!("  --html              With doc, write HTML instead of Markdown");
+    println!("  --strict            Turn silent fallbacks into errors, warn on =:: over constants");
+    println!("  --mock-clock        Timers advance virtual time instead of waiting");
+    println!("  --max-depth <n>     Maximum call depth (default 10000)");
+    println!("  --lang <en|zh>      Language of error messages and keywords");
+    println!("  --keywords <en|zh>  Localized keywords (如果, 函数>, ...), like //! lang zh; defaults to --lang");
+    println!();
+    println!("Environment:");
+    println!("  FLYUX_LANG=zh       Show error messages in Chinese (overridden by --lang)");
 }
\ No newline at end of file
@@ -0,0 +1,68 @@
+// flyux doc：把顶层 F> 的签名与紧挨着的 `///` 文档渲染成 Markdown 或 HTML
+use std::fs;
+use std::path::Path;
+use crate::ast::Function;
+use crate::cli::{DocFormat, RunOptions};
+use crate::lexer::tokenize;
+use crate::messages;
+use crate::parser::parse;
+
+// 与源码相同的写法：F>Point.scale(p, k(float))
+fn signature(f: &Function) -> String {
+    let params: Vec<String> = f.params.iter()
+        .map(|(name, typ)| match typ {
+            Some(t) => format!("{}({})", name, t),
+            None => name.clone(),
+        })
+        .collect();
+    match &f.owner {
+        Some(owner) => format!("F>{}.{}({})", owner, f.name, params.join(", ")),
+        None => format!("F>{}({})", f.name, params.join(", ")),
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn markdown(title: &str, fns: &[Function]) -> String {
+    let mut out = format!("# {}\n", title);
+    for f in fns {
+        out.push_str(&format!("\n## `{}`\n", signature(f)));
+        if let Some(doc) = &f.doc {
+            out.push_str(&format!("\n{}\n", doc));
+        }
+    }
+    out
+}
+
+fn html(title: &str, fns: &[Function]) -> String {
+    let title = escape_html(title);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        title
+    );
+    for f in fns {
+        out.push_str(&format!("<h2><code>{}</code></h2>\n", escape_html(&signature(f))));
+        // 空行分段
+        for para in f.doc.iter().flat_map(|d| d.split("\n\n")).filter(|p| !p.trim().is_empty()) {
+            out.push_str(&format!("<p>{}</p>\n", escape_html(para.trim())));
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+pub fn render_docs(path: &str, format: DocFormat, options: &RunOptions) {
+    match fs::read_to_string(path) {
+        Ok(content) => {
+            let program = parse(&tokenize(&content, options.keywords.as_deref()));
+            let title = Path::new(path).file_name().map_or(path.into(), |n| n.to_string_lossy());
+            match format {
+                DocFormat::Markdown => print!("{}", markdown(&title, &program.functions)),
+                DocFormat::Html => print!("{}", html(&title, &program.functions)),
+            }
+        }
+        Err(err) => eprintln!("{}", messages::error("E001", None, &[err.to_string()])),
+    }
+}
@@ -1,45 +1,564 @@
 use std::fs;
+use std::cell::RefCell;
+use std::sync::{mpsc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::collections::HashMap;
-use crate::lexer::tokenize;
-use crate::parser::parse;
-use crate::ast::{Expr, Stmt, Function};
+use crate::lexer::{tokenize, pragmas, confusable_warnings, NumLit, VALUE_TAG};
+use crate::parser::{parse, site_position};
+use crate::ast::{Expr, Stmt, LoopKind, Function, TypeDecl, EnumDecl, Program};
+use crate::cli::RunOptions;
+use crate::unicode::graphemes;
+use crate::messages::{self, fail};
+
+const BUILTIN_TYPES: [&str; 7] = ["int", "float", "bool", "string", "obj", "range", "iter"];
+
+// 内置协议：length/sort 与 L> 遍历只通过它们调用用户类型的方法
+const BUILTIN_INTERFACES: [(&str, &[(&str, usize)]); 2] = [
+    ("Sized", &[("length", 0)]),
+    ("Iterable", &[("length", 0), ("get", 1)]),
+];
+
+// Program-wide tables shared by every call frame.
+pub struct Env<'a> {
+    pub fns: HashMap<String, &'a Function>,
+    pub types: HashMap<String, &'a TypeDecl>,
+    pub enums: HashMap<String, &'a EnumDecl>,
+    pub methods: HashMap<(String, String), &'a Function>, // (type, method)
+    pub interfaces: HashMap<String, Vec<(String, usize)>>,  // interface -> (method, arity)
+    pub conformance: HashMap<String, Vec<String>>,          // type -> claimed interfaces
+    dispatch_cache: Mutex<HashMap<usize, (String, Dispatch<'a>)>>, // site -> (receiver type, target)
+    bindings: Mutex<HashMap<usize, Binding>>, // live bindings of every frame, by slot id
+    lazies: Mutex<Lazies<'a>>,           // `<iter#id>` -> lazy sequence
+    next_lazy: AtomicUsize,              // ids for handles, binding slots and call frames
+    scheduler: Mutex<Scheduler<'a>>,
+    channels: Mutex<HashMap<usize, std::sync::Arc<Channel>>>,
+    threads: Mutex<HashMap<usize, mpsc::Receiver<std::thread::Result<String>>>>,
+    spawner: Mutex<Option<mpsc::Sender<Spawn<'a>>>>, // set while execute_file runs
+    pub options: RunOptions,
+}
+
+// An `<iter#id>` entry. Its owners are call frames (by uid) or other handles
+// that hold it; it is removed once the last owner goes. Owner 0 is the whole
+// run. A consumed handle stays as an empty slot so that what it held keeps
+// its owner.
+struct LazySlot<'a> {
+    owners: Vec<usize>,
+    lazy: Option<Lazy<'a>>,
+}
+
+// The handle registry, indexed both ways so that a returning frame only
+// visits what it holds.
+#[derive(Default)]
+struct Lazies<'a> {
+    slots: HashMap<usize, LazySlot<'a>>,
+    held: HashMap<usize, Vec<usize>>, // owner -> handles it holds
+}
+
+impl<'a> Lazies<'a> {
+    fn hold(&mut self, id: usize, owner: usize) {
+        if let Some(slot) = self.slots.get_mut(&id) {
+            slot.owners.push(owner);
+            self.held.entry(owner).or_default().push(id);
+        }
+    }
+}
+
+// A reactive variable from `name => expr` or `name <=> other`. The variable's
+// slot in ctx holds VALUE_TAG + `bind#id`, which no user string can spell; the
+// value is recomputed lazily after any dependency in the same frame is written,
+// and the binding is dropped when its frame returns.
+struct Binding {
+    name: String,
+    expr: Expr,
+    deps: Vec<String>,
+    cache: Option<String>,
+    two_way: bool,
+}
+
+// Resolved target of a `.>` call site.
+#[derive(Clone, Copy)]
+enum Dispatch<'a> {
+    Builtin,
+    Method(&'a Function),
+    Free(&'a Function),
+}
+
+impl<'a> Env<'a> {
+    pub fn new(program: &'a Program, options: &RunOptions) -> Self {
+        let mut fns = HashMap::new();
+        let mut methods = HashMap::new();
+        for f in &program.functions {
+            match &f.owner {
+                Some(owner) => {
+                    if methods.insert((owner.clone(), f.name.clone()), f).is_some() {
+                        fail!("R001", owner, f.name);
+                    }
+                }
+                None => {
+                    fns.insert(f.name.clone(), f);
+                }
+            }
+        }
+        let mut types = HashMap::new();
+        for t in &program.types {
+            if BUILTIN_TYPES.contains(&t.name.as_str()) || types.insert(t.name.clone(), t).is_some() {
+                fail!("R002", t.name);
+            }
+        }
+        let mut enums = HashMap::new();
+        for e in &program.enums {
+            if BUILTIN_TYPES.contains(&e.name.as_str()) || types.contains_key(&e.name)
+                || enums.insert(e.name.clone(), e).is_some() {
+                fail!("R002", e.name);
+            }
+        }
+        let mut interfaces = HashMap::new();
+        for (name, required) in BUILTIN_INTERFACES {
+            let required = required.iter().map(|(m, n)| (m.to_string(), *n)).collect();
+            interfaces.insert(name.to_string(), required);
+        }
+        for i in &program.interfaces {
+            if interfaces.insert(i.name.clone(), i.methods.clone()).is_some() {
+                fail!("R003", i.name);
+            }
+        }
+        let mut conformance = HashMap::new();
+        for t in &program.types {
+            conformance.insert(t.name.clone(), t.interfaces.clone());
+        }
+        for e in &program.enums {
+            conformance.insert(e.name.clone(), e.interfaces.clone());
+        }
+        let env = Env {
+            fns,
+            types,
+            enums,
+            methods,
+            interfaces,
+            conformance,
+            dispatch_cache: Mutex::new(HashMap::new()),
+            bindings: Mutex::new(HashMap::new()),
+            lazies: Mutex::new(Lazies::default()),
+            next_lazy: AtomicUsize::new(0),
+            scheduler: Mutex::new(Scheduler::new(options.mock_clock)),
+            channels: Mutex::new(HashMap::new()),
+            threads: Mutex::new(HashMap::new()),
+            spawner: Mutex::new(None),
+            options: options.clone(),
+        };
+        for (tname, claimed) in &env.conformance {
+            for iname in claimed {
+                let required = env.interfaces.get(iname)
+                    .unwrap_or_else(|| fail!("R004", iname, tname));
+                for (method, arity) in required {
+                    match env.methods.get(&(tname.clone(), method.clone())) {
+                        Some(f) if f.params.len() == arity + 1 => {}
+                        Some(f) => fail!("R005", tname, iname, method, f.params.len().saturating_sub(1), arity),
+                        None => fail!("R006", tname, iname, method),
+                    }
+                }
+            }
+        }
+        for (owner, name) in env.methods.keys() {
+            if !env.is_known_type(owner) {
+                fail!("R007", owner, name);
+            }
+        }
+        for t in &program.types {
+            for (field, ftype) in &t.fields {
+                if let Some(ft) = ftype
+                    && !env.is_known_type(ft) {
+                    fail!("R008", ft, t.name, field);
+                }
+            }
+        }
+        env
+    }
+
+    fn is_known_type(&self, t: &str) -> bool {
+        BUILTIN_TYPES.contains(&t) || self.is_user_type(t)
+    }
+
+    fn is_user_type(&self, t: &str) -> bool {
+        self.types.contains_key(t) || self.enums.contains_key(t)
+    }
+
+    // Mark every binding of this frame that (transitively) reads `name` as dirty.
+    fn invalidate(&self, name: &str, ctx: &HashMap<String, (String, Option<String>, bool)>) {
+        let mut bindings = self.bindings.lock().unwrap();
+        if bindings.is_empty() {
+            return;
+        }
+        let live: Vec<usize> = ctx.values().filter_map(|(v, _, _)| binding_id(v)).collect();
+        let mut pending = vec![name.to_string()];
+        let mut seen = Vec::new();
+        while let Some(changed) = pending.pop() {
+            if seen.contains(&changed) {
+                continue;
+            }
+            for id in &live {
+                if let Some(b) = bindings.get_mut(id)
+                    && b.deps.contains(&changed) {
+                    b.cache = None;
+                    pending.push(b.name.clone());
+                }
+            }
+            seen.push(changed);
+        }
+    }
+
+    // A frame is returning: its bindings go with it.
+    fn release_bindings(&self, ctx: &HashMap<String, (String, Option<String>, bool)>) {
+        let mut bindings = self.bindings.lock().unwrap();
+        if !bindings.is_empty() {
+            for (v, _, _) in ctx.values() {
+                if let Some(id) = binding_id(v) {
+                    bindings.remove(&id);
+                }
+            }
+        }
+    }
+
+    // A method reachable through one of the interfaces the value's type claims.
+    fn protocol_method(&self, val: &str, method: &str) -> Option<&'a Function> {
+        let tname = infer_type(val, self)?;
+        let claimed = self.conformance.get(&tname)?;
+        let provided = claimed.iter().any(|i| {
+            self.interfaces.get(i).is_some_and(|ms| ms.iter().any(|(m, _)| m == method))
+        });
+        if provided {
+            self.methods.get(&(tname, method.to_string())).copied()
+        } else {
+            None
+        }
+    }
+
+    fn next_id(&self) -> usize {
+        self.next_lazy.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // Store a lazy sequence, owned by the current frame, and return its
+    // `<iter#id>` handle. Handles mentioned in `captured` are held by it.
+    fn register_lazy(&self, captured: &str, make: impl FnOnce(usize) -> Lazy<'a>) -> String {
+        let id = self.next_id();
+        let lazy = make(id);
+        let mut lazies = self.lazies.lock().unwrap();
+        for held in handle_ids(captured) {
+            lazies.hold(held, id);
+        }
+        lazies.slots.insert(id, LazySlot { owners: Vec::new(), lazy: Some(lazy) });
+        lazies.hold(id, OWNER.get());
+        handle("iter", id)
+    }
+
+    // Handles mentioned in `val` that `uid` holds (directly or through other
+    // handles) are also held by `new_owner` from now on.
+    fn adopt_lazies(&self, uid: usize, val: &str, new_owner: usize) {
+        let mut lazies = self.lazies.lock().unwrap();
+        if lazies.slots.is_empty() {
+            return;
+        }
+        let wanted = handle_ids(val);
+        if wanted.is_empty() {
+            return;
+        }
+        let mut held = std::collections::HashSet::from([uid]);
+        let mut queue = vec![uid];
+        while let Some(owner) = queue.pop() {
+            for &id in lazies.held.get(&owner).into_iter().flatten() {
+                if held.insert(id) {
+                    queue.push(id);
+                }
+            }
+        }
+        for id in wanted {
+            if held.contains(&id) && lazies.slots.get(&id).is_some_and(|slot| !slot.owners.contains(&new_owner)) {
+                lazies.hold(id, new_owner);
+            }
+        }
+    }
+
+    // A frame is returning: handles in its return value go to the caller,
+    // the rest of what it held is removed.
+    fn release_lazies(&self, uid: usize, val: &str, new_owner: usize) {
+        self.adopt_lazies(uid, val, new_owner);
+        let mut removed = Vec::new();
+        let mut gone = vec![uid];
+        {
+            let mut lazies = self.lazies.lock().unwrap();
+            while let Some(owner) = gone.pop() {
+                for id in lazies.held.remove(&owner).unwrap_or_default() {
+                    let Some(slot) = lazies.slots.get_mut(&id) else { continue };
+                    slot.owners.retain(|o| *o != owner);
+                    if slot.owners.is_empty() {
+                        removed.extend(lazies.slots.remove(&id).and_then(|slot| slot.lazy));
+                        gone.push(id);
+                    }
+                }
+            }
+        }
+        // 放开锁之后再丢弃：被丢弃的生成器还要释放它的绑定
+        for lazy in removed {
+            lazy.release(self);
+        }
+    }
+
+    // Handles in `val` outlive every frame (sent to a channel or another task).
+    fn escape_lazies(&self, val: &str) {
+        let mut lazies = self.lazies.lock().unwrap();
+        for id in handle_ids(val) {
+            lazies.hold(id, 0);
+        }
+    }
+
+    // Run `job` on a new system thread of the current run.
+    fn spawn(&self, job: impl FnOnce(&Env<'a>) + Send + 'a) {
+        let spawner = self.spawner.lock().unwrap();
+        spawner.as_ref().expect("no program is running")
+            .send(Spawn::Job(Box::new(job)))
+            .expect("program already finished");
+    }
+
+    fn channel(&self, val: &str) -> std::sync::Arc<Channel> {
+        let ch = chan_id(val).and_then(|id| self.channels.lock().unwrap().get(&id).cloned());
+        ch.unwrap_or_else(|| fail!("R009", val))
+    }
+
+    // Take a lazy sequence out of the registry; iterators are single-pass.
+    fn take_lazy(&self, id: usize) -> Lazy<'a> {
+        // 先放开锁再报错，免得 try 接住错误后锁已中毒
+        let lazy = {
+            let mut lazies = self.lazies.lock().unwrap();
+            let lazy = lazies.slots.get_mut(&id).and_then(|slot| slot.lazy.take());
+            // 不再经由它持有别的句柄时，空位也一并移除
+            if lazies.held.get(&id).is_none_or(|ids| ids.iter().all(|held| !lazies.slots.contains_key(held))) {
+                lazies.slots.remove(&id);
+                lazies.held.remove(&id);
+            }
+            lazy
+        };
+        lazy.unwrap_or_else(|| fail!("R010", id))
+    }
+
+    // builtin -> method of the receiver's type -> free function; cached per call site
+    fn resolve_method(&self, site: usize, recv_type: &str, name: &str) -> Option<Dispatch<'a>> {
+        if let Some((cached_type, target)) = self.dispatch_cache.lock().unwrap().get(&site)
+            && cached_type == recv_type {
+            return Some(*target);
+        }
+        let target = if BUILTIN_METHODS.contains(&name) {
+            Dispatch::Builtin
+        } else if let Some(m) = self.methods.get(&(recv_type.to_string(), name.to_string())) {
+            Dispatch::Method(m)
+        } else if let Some(f) = self.fns.get(name) {
+            Dispatch::Free(f)
+        } else {
+            return None;
+        };
+        self.dispatch_cache.lock().unwrap().insert(site, (recv_type.to_string(), target));
+        Some(target)
+    }
+}
 
 // Recursively update nested object string given a path of keys.
-fn update_object_str(s: &str, path: &[String], new_value: String) -> String {
+fn update_object_str(s: &str, path: &[String], new_value: String, env: &Env) -> String {
+    if let Some((tname, body)) = split_record(s, env) {
+        let decl = env.types[tname];
+        let mut map = parse_object_string(body);
+        let key = &path[0];
+        let updated = if path.len() == 1 {
+            check_field(decl, key, new_value, env)
+        } else {
+            let inner_str = map.get(key)
+                .unwrap_or_else(|| fail!("R011", tname, key));
+            update_object_str(inner_str, &path[1..], new_value, env)
+        };
+        map.insert(key.clone(), updated);
+        return serialize_record(decl, &map);
+    }
     let mut map = parse_object_string(s);
     if path.len() == 1 {
         map.insert(path[0].clone(), new_value);
     } else {
         let key = &path[0];
         if let Some(inner_str) = map.get(key) {
-            let updated = update_object_str(inner_str, &path[1..], new_value);
+            let updated = update_object_str(inner_str, &path[1..], new_value, env);
             map.insert(key.clone(), updated);
         } else {
-            panic!("Key '{}' not found during nested assignment", key);
+            fail!("R012", key);
         }
     }
     serialize_object_map(&map)
 }
 
-fn call_builtin(name: &str, args: Vec<String>) -> Option<String> {
+const BUILTIN_METHODS: [&str; 28] = [
+    "length", "chars", "bytes", "sort", "push", "typeof", "variant", "try",
+    "map", "filter", "take", "zip", "enumerate", "collect",
+    "spawn", "await", "sleep", "after", "every", "cancel", "now",
+    "thread", "spawn_thread", "join", "chan", "send", "recv", "close",
+];
+
+fn call_builtin(name: &str, args: Vec<String>, env: &Env) -> Option<String> {
+    // 这些内置函数都要读第一个参数
+    if args.is_empty() && matches!(name, "length" | "chars" | "bytes" | "sort" | "enumerate" | "collect" | "recv" | "close") {
+        fail!("R103", name);
+    }
     match name {
+        "typeof" => {
+            let s = args.first().map(String::as_str).unwrap_or("");
+            Some(infer_type(s, env).unwrap_or_else(|| "string".into()))
+        }
+        "variant" => {
+            let s = args.first().map(String::as_str).unwrap_or("");
+            match split_variant(s, env) {
+                Some((_, variant, _)) => Some(variant.to_string()),
+                None => fail!("R013", s),
+            }
+        }
         "length" => {
             let s = &args[0];
+            if let Some(f) = env.protocol_method(s, "length") {
+                return Some(call_with_receiver(f, args, env));
+            }
+            if let Some(r) = parse_range(s) {
+                return Some(r.len().to_string());
+            }
             if s.starts_with('[') && s.ends_with(']') {
                 return Some(parse_array_string(s).len().to_string());
             }
             if s.starts_with('{') && s.ends_with('}') {
                 return Some(parse_object_string(s).len().to_string());
             }
-            Some(s.len().to_string())
+            Some(graphemes(s).len().to_string())
         }
+        // length 按字素簇计数；chars 与 bytes 分别给出码位数与 UTF-8 字节数
+        "chars" => Some(args[0].chars().count().to_string()),
+        "bytes" => Some(args[0].len().to_string()),
         "sort" => {
-            let mut vec = parse_array_string(&args[0]);
+            let mut vec = match iterate_protocol(&args[0], env) {
+                Some(items) => items.collect(),
+                None => parse_array_string(&args[0]),
+            };
             vec.sort();                 // 简单字典序
             Some(serialize_array_vec(&vec))
         }
+        // 惰性适配器：返回新的 <iter#id>，不生成数组
+        "map" | "filter" => {
+            if args.len() < 2 { fail!("R014", name); }
+            let src = Box::new(Lazy::from_value(&args[0], env));
+            let f = args[1].clone();
+            if !env.fns.contains_key(&f) { fail!("R015", f); }
+            Some(env.register_lazy(&args[0], |_| if name == "map" { Lazy::Map(src, f) } else { Lazy::Filter(src, f) }))
+        }
+        "take" => {
+            let n = args.get(1).and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or_else(|| fail!("R016"));
+            let src = Box::new(Lazy::from_value(&args[0], env));
+            Some(env.register_lazy(&args[0], |_| Lazy::Take(src, n)))
+        }
+        "zip" => {
+            if args.len() < 2 { fail!("R017", "zip"); }
+            let a = Box::new(Lazy::from_value(&args[0], env));
+            let b = Box::new(Lazy::from_value(&args[1], env));
+            Some(env.register_lazy(&args[..2].join(","), |_| Lazy::Zip(a, b)))
+        }
+        "enumerate" => {
+            let src = Box::new(Lazy::from_value(&args[0], env));
+            Some(env.register_lazy(&args[0], |_| Lazy::Enumerate(src, 0)))
+        }
+        "collect" => {
+            let mut lazy = Lazy::from_value(&args[0], env);
+            let items: Vec<String> = std::iter::from_fn(|| lazy.next(env)).collect();
+            Some(serialize_array_vec(&items))
+        }
+        // 协程与定时器
+        "spawn" => {
+            let f = args.first().unwrap_or_else(|| fail!("R014", "spawn"));
+            if !env.fns.contains_key(f) { fail!("R015", f); }
+            env.escape_lazies(&args[1..].join(","));
+            let mut sched = env.scheduler.lock().unwrap();
+            let id = sched.spawn(f.clone(), args[1..].to_vec());
+            sched.joinable.insert(id);
+            Some(handle("task", id))
+        }
+        // 任务的根 Fiber 里的 sleep/await 由 Fiber 挂起；走到这里的在生成器、回调或 thread() 里
+        "await" | "sleep" => Some(match wait_report(name, &args, env) {
+            Ok(report) => block_on(report, name, env),
+            Err(value) => value,
+        }),
+        // spawn 已是协程原语，系统线程用 thread；spawn_thread 是它的别名
+        "thread" | "spawn_thread" => {
+            let f = args.first().unwrap_or_else(|| fail!("R014", name)).clone();
+            if !env.fns.contains_key(&f) { fail!("R015", f); }
+            let rest = args[1..].to_vec();
+            env.escape_lazies(&rest.join(","));
+            let (result_tx, result_rx) = mpsc::channel();
+            env.spawn(move |env| {
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| call_named(&f, rest, env)));
+                let _ = result_tx.send(result);
+            });
+            let id = env.next_id();
+            env.threads.lock().unwrap().insert(id, result_rx);
+            Some(handle("thread", id))
+        }
+        "join" => {
+            let v = args.first().cloned().unwrap_or_default();
+            let id = thread_id(&v).unwrap_or_else(|| fail!("R019", v));
+            let result = env.threads.lock().unwrap().remove(&id);
+            let result = result.unwrap_or_else(|| fail!("R020", v));
+            let result = result.recv().expect("thread stopped without a result");
+            Some(result.unwrap_or_else(|payload| std::panic::resume_unwind(payload)))
+        }
+        "chan" => {
+            let id = env.next_id();
+            let ch = Channel { state: Mutex::new(Default::default()), ready: std::sync::Condvar::new() };
+            env.channels.lock().unwrap().insert(id, std::sync::Arc::new(ch));
+            Some(handle("chan", id))
+        }
+        "send" => {
+            if args.len() < 2 { fail!("R017", "send"); }
+            env.escape_lazies(&args[1]);
+            env.channel(&args[0]).send(args[1].clone());
+            Some(NULL.into())
+        }
+        "recv" => Some(env.channel(&args[0]).recv().unwrap_or_else(|| NULL.into())),
+        "close" => {
+            env.channel(&args[0]).close();
+            Some(NULL.into())
+        }
+        "after" | "every" => {
+            let ms = args.first().and_then(|n| n.parse::<f64>().ok())
+                .unwrap_or_else(|| fail!("R018", name)).max(0.0) as u64;
+            let f = args.get(1).unwrap_or_else(|| fail!("R014", name)).clone();
+            if !env.fns.contains_key(&f) { fail!("R015", f); }
+            let mut sched = env.scheduler.lock().unwrap();
+            let id = sched.next_id();
+            let kind = if name == "after" { TimerKind::After(f) } else { TimerKind::Every(f, ms.max(1), id) };
+            sched.add_timer(ms, kind);
+            Some(handle("timer", id))
+        }
+        "cancel" => {
+            let id = args.first()
+                .and_then(|v| handle_id(v, "timer"))
+                .unwrap_or_else(|| fail!("R021"));
+            let mut sched = env.scheduler.lock().unwrap();
+            sched.cancelled.push(id);
+            sched.timers.retain(|t| !matches!(t.kind, TimerKind::Every(_, _, tid) if tid == id));
+            Some(NULL.into())
+        }
+        "now" => Some(env.scheduler.lock().unwrap().clock.now_ms().to_string()),
+        // try(f, args...)：运行时错误变成 {"value":null,"error":R038} 这样的结果，而不是结束程序
+        "try" => {
+            let f = args.first().unwrap_or_else(|| fail!("R014", "try")).clone();
+            if !env.fns.contains_key(&f) { fail!("R015", f); }
+            let (value, error) = match messages::catching(|| call_named(&f, args[1..].to_vec(), env)) {
+                Ok(v) => (v, NULL.to_string()),
+                Err(code) => (NULL.to_string(), code),
+            };
+            Some(format!("{{\"value\":{},\"error\":{}}}", quote_item(&value), quote_item(&error)))
+        }
         "push" => {
-            if args.len() < 2 { panic!("push 需要一个额外参数"); }
+            if args.len() < 2 { fail!("R017", "push"); }
             let mut vec = parse_array_string(&args[0]);
             vec.push(args[1].clone());
             Some(serialize_array_vec(&vec))
@@ -49,680 +568,2783 @@ fn call_builtin(name: &str, args: Vec<String>) -> Option<String> {
 }
 // Parse an object string like {"a":1,"b":2} into a HashMap<String, String>
 fn parse_object_string(s: &str) -> std::collections::HashMap<String, String> {
+    parse_object_entries(s).into_iter().collect()
+}
+
+// Object entries in source order, for iteration
+fn parse_object_entries(s: &str) -> Vec<(String, String)> {
     let s = s.trim();
     if !s.starts_with('{') || !s.ends_with('}') {
-        panic!("Not an object string: '{}'", s);
+        fail!("R022", s);
     }
-    let inner = &s[1..s.len()-1];
-    let mut map = HashMap::new();
-    let mut depth = 0;
+    split_items(&s[1..s.len()-1])
+        .into_iter()
+        .filter_map(|kv| {
+            let (k, v) = split_key(&kv)?;
+            Some((unquote_item(k.trim()), unquote_item(v.trim())))
+        })
+        .collect()
+}
+
+fn serialize_object_map(map: &std::collections::HashMap<String, String>) -> String {
+    let kvs: Vec<String> = map.iter()
+        .map(|(k, v)| format!("{}:{}", quote_str(k), quote_item(v)))
+        .collect();
+    format!("{{{}}}", kvs.join(","))
+}
+
+// Parse an array string like [1,2,3] into Vec<String>
+fn parse_array_string(s: &str) -> Vec<String> {
+    let s = s.trim();
+    if !s.starts_with('[') || !s.ends_with(']') {
+        fail!("R023", s);
+    }
+    split_items(&s[1..s.len()-1]).iter().map(|item| unquote_item(item)).collect()
+}
+
+fn serialize_array_vec(vec: &[String]) -> String {
+    let items: Vec<String> = vec.iter().map(|v| quote_item(v)).collect();
+    format!("[{}]", items.join(","))
+}
+
+// 容器里的字符串写成带引号的形式（转义 \ 与 "），其中的逗号、括号不会被当成结构；
+// 数值、布尔、带标记的值与嵌套的容器原样写入
+fn quote_item(v: &str) -> String {
+    let bare = v.starts_with(VALUE_TAG)
+        || v == "true" || v == "false"
+        || (v == v.trim() && v.parse::<f64>().is_ok())
+        || (v.starts_with('[') && v.ends_with(']'))
+        || (v.starts_with('{') && v.ends_with('}'));
+    if bare { v.to_string() } else { quote_str(v) }
+}
+
+fn quote_str(v: &str) -> String {
+    format!("\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// The value an item of a container stands for.
+fn unquote_item(item: &str) -> String {
+    let Some(inner) = item.strip_prefix('"').and_then(|i| i.strip_suffix('"')) else {
+        return item.to_string();
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        out.push(if c == '\\' { chars.next().unwrap_or(c) } else { c });
+    }
+    out
+}
+
+// Split the inside of a container at its top-level commas. Brackets and
+// parentheses nest, and a quoted string is skipped over whole.
+fn split_items(inner: &str) -> Vec<String> {
+    let mut items = vec![];
     let mut current = String::new();
-    let mut entries = vec![];
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut escaped = false;
     for c in inner.chars() {
+        if quoted {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                quoted = false;
+            }
+            current.push(c);
+            continue;
+        }
         match c {
-            '{' | '[' => {
+            '"' => {
+                quoted = true;
+                current.push(c);
+            }
+            '{' | '[' | '(' => {
                 depth += 1;
                 current.push(c);
             }
-            '}' | ']' => {
+            '}' | ']' | ')' => {
                 depth -= 1;
                 current.push(c);
             }
             ',' if depth == 0 => {
-                entries.push(current.trim().to_string());
+                items.push(current.trim().to_string());
                 current.clear();
             }
             _ => current.push(c),
         }
     }
     if !current.trim().is_empty() {
-        entries.push(current.trim().to_string());
+        items.push(current.trim().to_string());
+    }
+    items
+}
+
+// `"key":value` -> (key, value); the key may itself contain ':'.
+fn split_key(entry: &str) -> Option<(&str, &str)> {
+    if !entry.starts_with('"') {
+        return entry.split_once(':');
     }
-    for kv in entries {
-        if let Some((k, v)) = kv.split_once(':') {
-            let parsed_key = k.trim().trim_matches('"').to_string();
-            let val = v.trim().to_string();
-            map.insert(parsed_key, val);
+    let mut escaped = false;
+    let close = entry.char_indices().skip(1).find(|&(_, c)| {
+        let end = !escaped && c == '"';
+        escaped = !escaped && c == '\\';
+        end
+    })?.0;
+    let rest = entry[close + 1..].trim_start().strip_prefix(':')?;
+    Some((&entry[..=close], rest))
+}
+
+// Record values are VALUE_TAG, the type name and an object body: Point{"x":1,"y":2}.
+// The tag keeps a string spelled like a record from passing as one.
+fn split_record<'s>(s: &'s str, env: &Env) -> Option<(&'s str, &'s str)> {
+    let s = s.trim().strip_prefix(VALUE_TAG)?;
+    if !s.ends_with('}') {
+        return None;
+    }
+    let brace = s.find('{')?;
+    let name = &s[..brace];
+    if env.types.contains_key(name) {
+        Some((name, &s[brace..]))
+    } else {
+        None
+    }
+}
+
+// Range values are VALUE_TAG followed by their literal text (1..10, 0..=8:2,
+// 10..0) and never expanded into an array. The tag keeps a string such as
+// "1..5" from being read as a range; print shows the text without it.
+#[derive(Clone, Copy)]
+struct Range {
+    start: i64,
+    end: i64,
+    step: i64,
+    inclusive: bool,
+}
+
+impl Range {
+    // 默认步长：升序 1，降序 -1；步长 0 由调用者在此之前报错
+    fn new(start: i64, end: i64, inclusive: bool, step: Option<i64>) -> Range {
+        let step = step.unwrap_or(if start <= end { 1 } else { -1 });
+        Range { start, end, step, inclusive }
+    }
+
+    // 在 i128 中计算，-9e18..9e18 这样的宽区间也不会溢出；长度超出 usize 时报错
+    fn len(&self) -> usize {
+        let (start, end) = (self.start as i128, self.end as i128);
+        let span = if self.step > 0 { end - start } else { start - end };
+        let stride = self.step.unsigned_abs() as i128;
+        if span < 0 || (span == 0 && !self.inclusive) {
+            return 0;
+        }
+        let full = span / stride;
+        let exact = span % stride == 0;
+        let len = if exact && !self.inclusive { full } else { full + 1 };
+        usize::try_from(len).unwrap_or_else(|_| fail!("R099", self.to_value()))
+    }
+
+    // i < len 时结果一定落在 start 与 end 之间
+    fn nth(&self, i: usize) -> i64 {
+        (self.start as i128 + self.step as i128 * i as i128) as i64
+    }
+
+    fn get(&self, i: usize) -> Option<i64> {
+        (i < self.len()).then(|| self.nth(i))
+    }
+
+    fn to_value(self) -> String {
+        let op = if self.inclusive { "..=" } else { ".." };
+        let default_step = if self.start <= self.end { 1 } else { -1 };
+        if self.step == default_step {
+            format!("{}{}{}{}", VALUE_TAG, self.start, op, self.end)
+        } else {
+            format!("{}{}{}{}:{}", VALUE_TAG, self.start, op, self.end, self.step)
         }
     }
-    map
 }
 
-fn serialize_object_map(map: &std::collections::HashMap<String, String>) -> String {
-    let kvs: Vec<String> = map.iter()
-        .map(|(k, v)| format!("\"{}\":{}", k, v))
+fn parse_range(s: &str) -> Option<Range> {
+    let s = s.trim().strip_prefix(VALUE_TAG)?;
+    let (body, step) = match s.rsplit_once(':') {
+        Some((b, st)) => (b, Some(st.parse::<i64>().ok()?)),
+        None => (s, None),
+    };
+    // 跳过负号，避免把 -3..0 的 '-' 当作分隔
+    let dots = body.get(1..)?.find("..")? + 1;
+    let start = body[..dots].parse::<i64>().ok()?;
+    let (inclusive, end) = match body[dots + 2..].strip_prefix('=') {
+        Some(e) => (true, e),
+        None => (false, &body[dots + 2..]),
+    };
+    let end = end.parse::<i64>().ok()?;
+    if step == Some(0) {
+        return None;
+    }
+    Some(Range::new(start, end, inclusive, step))
+}
+
+// Enum values are VALUE_TAG followed by `Shape.Circle(2)`, or `Shape.Empty`
+// without payload, so that no string passes as one. Returns (enum, variant, payload).
+fn split_variant<'s>(s: &'s str, env: &Env) -> Option<(&'s str, &'s str, Vec<String>)> {
+    let s = s.trim().strip_prefix(VALUE_TAG)?;
+    let (enum_name, rest) = s.split_once('.')?;
+    let decl = env.enums.get(enum_name)?;
+    let (variant, payload) = match rest.find('(') {
+        Some(open) if rest.ends_with(')') => (&rest[..open], &rest[open + 1..rest.len() - 1]),
+        Some(_) => return None,
+        None => (rest, ""),
+    };
+    decl.variants.iter().find(|(v, _)| v == variant)?;
+    let values = split_items(payload).iter().map(|item| unquote_item(item)).collect();
+    Some((enum_name, variant, values))
+}
+
+fn make_variant(decl: &EnumDecl, variant: &str, payload: Vec<String>) -> String {
+    let fields = match decl.variants.iter().find(|(v, _)| v == variant) {
+        Some((_, f)) => f,
+        None => fail!("R025", decl.name, variant),
+    };
+    if fields.len() != payload.len() {
+        fail!("R026", decl.name, variant, fields.len(), payload.len());
+    }
+    if fields.is_empty() {
+        format!("{}{}.{}", VALUE_TAG, decl.name, variant)
+    } else {
+        let items: Vec<String> = payload.iter().map(|v| quote_item(v)).collect();
+        format!("{}{}.{}({})", VALUE_TAG, decl.name, variant, items.join(","))
+    }
+}
+
+// Fields are always written in declaration order.
+fn serialize_record(decl: &TypeDecl, map: &HashMap<String, String>) -> String {
+    let kvs: Vec<String> = decl.fields.iter()
+        .map(|(f, _)| format!("{}:{}", quote_str(f), map.get(f).map_or(NULL.into(), |v| quote_item(v))))
         .collect();
-    format!("{{{}}}", kvs.join(","))
+    format!("{}{}{{{}}}", VALUE_TAG, decl.name, kvs.join(","))
+}
+
+// A float's text always has a fractional part, so 5.0 stays a float.
+fn float_text(f: f64) -> String {
+    NumLit::Float(f).to_string()
+}
+
+fn is_float(val: &str) -> bool {
+    val.parse::<i64>().is_err() && val.parse::<f64>().is_ok()
+}
+
+// int 与 int 的 + - * 和整除按整数算，溢出或除不尽时得到 float；有 float 参与时结果总是 float
+fn arithmetic(op: &str, l: &str, r: &str, lnum: f64, rnum: f64) -> String {
+    let ints = l.parse::<i64>().ok().zip(r.parse::<i64>().ok());
+    if let Some((a, b)) = ints {
+        let exact = match op {
+            "+" => a.checked_add(b),
+            "-" => a.checked_sub(b),
+            "*" => a.checked_mul(b),
+            _ => a.checked_rem(b).filter(|&m| m == 0).and_then(|_| a.checked_div(b)),
+        };
+        if let Some(n) = exact {
+            return n.to_string();
+        }
+    }
+    let x = match op {
+        "+" => lnum + rnum,
+        "-" => lnum - rnum,
+        "*" => lnum * rnum,
+        _ => lnum / rnum,
+    };
+    if ints.is_some() || is_float(l) || is_float(r) { float_text(x) } else { format!("{}", x) }
+}
+
+// Check a value against a declared type, returning its normalized form on success.
+fn conform_to_type(val: &str, typ: &str, env: &Env) -> Option<String> {
+    match typ {
+        "int" => val.parse::<i64>().ok().map(|_| val.to_string()),
+        "float" => val.parse::<f64>().ok().map(float_text),
+        "bool" => match val.trim_matches('"').to_lowercase().as_str() {
+            "true" | "1" => Some("true".to_string()),
+            "false" | "0" => Some("false".to_string()),
+            _ => None,
+        },
+        "string" => Some(val.to_string()),
+        "range" => parse_range(val).map(|r| r.to_value()),
+        "iter" => lazy_id(val).map(|_| val.to_string()),
+        "obj" => {
+            let t = val.trim();
+            let is_obj = (t.starts_with('[') && t.ends_with(']')) || (t.starts_with('{') && t.ends_with('}'));
+            is_obj.then(|| val.to_string())
+        }
+        other if env.enums.contains_key(other) => match split_variant(val, env) {
+            Some((name, _, _)) if name == other => Some(val.to_string()),
+            _ => None,
+        },
+        other => match split_record(val, env) {
+            Some((name, _)) if name == other => Some(val.to_string()),
+            _ => None,
+        },
+    }
+}
+
+// Explicit conversion used by `=::`.
+//   int:    float truncates toward zero, bool -> 1/0, numeric strings parse
+//   float:  int/bool/numeric strings
+//   bool:   numbers are true when non-zero, "" / "false" / "true", obj is true unless empty
+//   string: the value's text
+//   obj:    only array/object values
+//   user types: the value must already be of that type
+fn convert_value(val: &str, typ: &str, env: &Env) -> Result<String, String> {
+    let t = val.trim();
+    let is_obj = (t.starts_with('[') && t.ends_with(']')) || (t.starts_with('{') && t.ends_with('}'));
+    let fail = || Err(messages::error("R088", None, &[val.to_string(), typ.to_string()]));
+    let as_bool = || match t.to_lowercase().as_str() {
+        "true" => Some(true),
+        "false" | "" => Some(false),
+        _ => None,
+    };
+    match typ {
+        "int" => {
+            if let Ok(i) = t.parse::<i64>() {
+                Ok(i.to_string())
+            } else if let Ok(f) = t.parse::<f64>() {
+                if f.is_finite() { Ok((f.trunc() as i64).to_string()) } else { fail() }
+            } else if let Some(b) = as_bool() {
+                Ok(if b { "1" } else { "0" }.to_string())
+            } else {
+                fail()
+            }
+        }
+        "float" => {
+            if let Ok(f) = t.parse::<f64>() {
+                Ok(float_text(f))
+            } else if let Some(b) = as_bool() {
+                Ok(if b { "1.0" } else { "0.0" }.to_string())
+            } else {
+                fail()
+            }
+        }
+        "bool" => {
+            let b = if let Ok(f) = t.parse::<f64>() {
+                f != 0.0
+            } else if let Some(b) = as_bool() {
+                b
+            } else if is_obj {
+                t.len() > 2
+            } else {
+                return fail();
+            };
+            Ok(b.to_string())
+        }
+        "string" => Ok(val.to_string()),
+        "obj" => if is_obj { Ok(t.to_string()) } else { fail() },
+        other if env.is_user_type(other) => conform_to_type(val, other, env).map_or_else(fail, Ok),
+        other => Err(messages::error("R039", None, &[other.to_string()])),
+    }
+}
+
+// Validate a value assigned to `decl.field`.
+fn check_field(decl: &TypeDecl, field: &str, val: String, env: &Env) -> String {
+    let ftype = match decl.fields.iter().find(|(f, _)| f == field) {
+        Some((_, t)) => t,
+        None => fail!("R011", decl.name, field),
+    };
+    match ftype {
+        Some(t) => conform_to_type(&val, t, env).unwrap_or_else(|| {
+            fail!("R027", decl.name, field, t, val)
+        }),
+        None => val,
+    }
+}
+
+fn binding_id(slot: &str) -> Option<usize> {
+    slot.strip_prefix(VALUE_TAG)?.strip_prefix("bind#")?.parse().ok()
+}
+
+// Current value of a reactive binding, recomputed if a dependency changed.
+fn read_binding(id: usize, ctx: &mut HashMap<String, (String, Option<String>, bool)>, env: &Env) -> String {
+    let expr = {
+        let bindings = env.bindings.lock().unwrap();
+        if let Some(v) = &bindings[&id].cache {
+            return v.clone();
+        }
+        bindings[&id].expr.clone()
+    };
+    let val = eval_expr(&expr, ctx, env);
+    if let Some(b) = env.bindings.lock().unwrap().get_mut(&id) {
+        b.cache = Some(val.clone());
+    }
+    val
+}
+
+// Every identifier an expression reads.
+fn collect_idents(expr: &Expr, out: &mut Vec<String>) {
+    match expr {
+        Expr::Ident(id) | Expr::PostfixIncrement(id) | Expr::PostfixDecrement(id) => {
+            if !out.contains(id) {
+                out.push(id.clone());
+            }
+        }
+        Expr::Number(_) | Expr::Str(_) => {}
+        Expr::Input(args) | Expr::Call(_, args, _) | Expr::Array(args) | Expr::Variant(_, _, args) => {
+            args.iter().for_each(|a| collect_idents(a, out));
+        }
+        Expr::Binary(l, _, r, _) | Expr::Logical(_, l, r) | Expr::Index(l, r, _) | Expr::Coalesce(l, r) => {
+            collect_idents(l, out);
+            collect_idents(r, out);
+        }
+        Expr::Slice(target, start, end, _) => {
+            collect_idents(target, out);
+            start.iter().chain(end).for_each(|e| collect_idents(e, out));
+        }
+        Expr::Range { start, end, step, .. } => {
+            collect_idents(start, out);
+            collect_idents(end, out);
+            if let Some(st) = step {
+                collect_idents(st, out);
+            }
+        }
+        Expr::Not(inner) | Expr::Access(inner, _, _) | Expr::SafeAccess(inner, _, _) | Expr::Is(inner, _, _, _) => {
+            collect_idents(inner, out)
+        }
+        Expr::Object(pairs) | Expr::Construct(_, pairs) => {
+            pairs.iter().for_each(|(_, v)| collect_idents(v, out));
+        }
+        Expr::MethodCall { target, args, .. } | Expr::SafeMethodCall { target, args, .. } => {
+            collect_idents(target, out);
+            args.iter().for_each(|a| collect_idents(a, out));
+        }
+    }
+}
+
+// Register `name => expr` (or `name <=> other`) in the current scope.
+fn declare_binding(
+    name: &str,
+    expr: Expr,
+    two_way: bool,
+    ctx: &mut HashMap<String, (String, Option<String>, bool)>,
+    env: &Env,
+) {
+    if let Some((_, _, true)) = ctx.get(name) {
+        fail!("R028", name);
+    }
+    let mut deps = Vec::new();
+    collect_idents(&expr, &mut deps);
+
+    // 依赖图中从 deps 出发若能回到 name，即为循环绑定
+    let mut path = vec![name.to_string()];
+    fn reaches(
+        target: &str,
+        deps: &[String],
+        ctx: &HashMap<String, (String, Option<String>, bool)>,
+        env: &Env,
+        path: &mut Vec<String>,
+    ) -> bool {
+        for d in deps {
+            path.push(d.clone());
+            if d == target {
+                return true;
+            }
+            if let Some(id) = ctx.get(d).and_then(|(v, _, _)| binding_id(v)) {
+                let next = env.bindings.lock().unwrap()[&id].deps.clone();
+                if reaches(target, &next, ctx, env, path) {
+                    return true;
+                }
+            }
+            path.pop();
+        }
+        false
+    }
+    if reaches(name, &deps, ctx, env, &mut path) {
+        fail!("R029", path.join(" -> "));
+    }
+
+    let id = env.next_id();
+    env.bindings.lock().unwrap().insert(id, Binding { name: name.to_string(), expr, deps, cache: None, two_way });
+    // 同名变量原先若是绑定，它随这次重新声明失效
+    if let Some(old) = ctx.insert(name.to_string(), (format!("{}bind#{}", VALUE_TAG, id), Some("bind".to_string()), false))
+        && let Some(old_id) = binding_id(&old.0) {
+        env.bindings.lock().unwrap().remove(&old_id);
+    }
+    env.invalidate(name, ctx);
+}
+
+// For a bound variable, the variable a write should go to (two-way), or a panic.
+fn binding_write_target(name: &str, ctx: &HashMap<String, (String, Option<String>, bool)>, env: &Env) -> Option<String> {
+    let id = binding_id(&ctx.get(name)?.0)?;
+    let target = match &env.bindings.lock().unwrap()[&id] {
+        Binding { expr: Expr::Ident(target), two_way: true, .. } => Some(target.clone()),
+        _ => None,
+    };
+    Some(target.unwrap_or_else(|| fail!("R030", name)))
+}
+
+// Call `f` with `passed[0]` bound to its first parameter (the `.>` receiver).
+fn call_with_receiver<'a>(f: &'a Function, passed: Vec<String>, env: &Env<'a>) -> String {
+    // receiver 是第一个形参；缺省的参数为 null，if(c) 把它当作假
+    run_function(f, bind_args(f, passed, env), env)
+}
+
+// Operator methods a user type may define; the left operand picks the method.
+fn operator_method(op: &str) -> Option<&'static str> {
+    match op {
+        "+" => Some("add"),
+        "-" => Some("sub"),
+        "*" => Some("mul"),
+        "/" => Some("div"),
+        "=" | "==" | "!=" => Some("eq"),
+        "<" => Some("lt"),
+        ">" => Some("gt"),
+        "<=" => Some("le"),
+        ">=" => Some("ge"),
+        _ => None,
+    }
+}
+
+// Dispatch `l op r` to a method of l's user type. Comparisons fall back on
+// `lt`/`eq` when `gt`, `le` or `ge` are not defined and always yield "true"/"false";
+// `!=` is the negation of `eq`, and without `eq` values compare as usual.
+fn overloaded_binary<'a>(op: &str, l: &str, r: &str, env: &Env<'a>) -> Option<String> {
+    let tname = match infer_type(l, env) {
+        Some(t) if env.types.contains_key(&t) || env.enums.contains_key(&t) => t,
+        _ => return None,
+    };
+    let method = operator_method(op)?;
+    let find = |m: &str| env.methods.get(&(tname.clone(), m.to_string())).copied();
+    let call = |f: &'a Function| call_with_receiver(f, vec![l.to_string(), r.to_string()], env);
+
+    if let Some(f) = find(method) {
+        let v = call(f);
+        return Some(match method {
+            "add" | "sub" | "mul" | "div" => v,
+            _ => bool_value(truthy(&v) != (op == "!=")),
+        });
+    }
+    if method == "eq" {
+        return None;
+    }
+    let lt = find("lt");
+    let eq = find("eq");
+    let result = match (method, lt, eq) {
+        ("le", Some(lt), Some(eq)) => truthy(&call(lt)) || truthy(&call(eq)),
+        ("gt", Some(lt), Some(eq)) => !(truthy(&call(lt)) || truthy(&call(eq))),
+        ("ge", Some(lt), _) => !truthy(&call(lt)),
+        _ => fail!("R031", tname, op, method),
+    };
+    Some(result.to_string())
+}
+
+// Lazily walk a user value through its Iterable `length`/`get` methods.
+fn iterate_protocol<'e>(val: &str, env: &'e Env) -> Option<impl Iterator<Item = String> + 'e> {
+    let len_fn = env.protocol_method(val, "length")?;
+    let get_fn = env.protocol_method(val, "get")?;
+    let len = call_with_receiver(len_fn, vec![val.to_string()], env);
+    let n = len.parse::<usize>()
+        .unwrap_or_else(|_| fail!("R032", val, len));
+    let receiver = val.to_string();
+    Some((0..n).map(move |i| call_with_receiver(get_fn, vec![receiver.clone(), i.to_string()], env)))
+}
+
+// Handles are VALUE_TAG followed by `<kind#id>`, as in `<iter#3>`. The tag
+// keeps a string such as "<iter#3>" from reaching the object it names; print
+// shows the text without it.
+fn handle(kind: &str, id: usize) -> String {
+    format!("{}<{}#{}>", VALUE_TAG, kind, id)
+}
+
+fn handle_id(val: &str, kind: &str) -> Option<usize> {
+    val.strip_prefix(VALUE_TAG)?.strip_prefix('<')?.strip_prefix(kind)?
+        .strip_prefix('#')?.strip_suffix('>')?.parse().ok()
+}
+
+fn lazy_id(val: &str) -> Option<usize> {
+    handle_id(val, "iter")
+}
+
+// Every `<iter#id>` handle inside a value, including inside arrays and objects.
+fn handle_ids(val: &str) -> Vec<usize> {
+    let prefix = format!("{}<iter#", VALUE_TAG);
+    val.match_indices(&prefix)
+        .filter_map(|(i, _)| {
+            let rest = &val[i + prefix.len()..];
+            rest[..rest.find('>')?].parse().ok()
+        })
+        .collect()
+}
+
+// A lazy sequence behind an `<iter#id>` handle: generators and the
+// map/filter/take/zip/enumerate adapters. Nothing is materialized.
+enum Lazy<'a> {
+    Gen(Generator<'a>),
+    Items(std::vec::IntoIter<String>),
+    Range(Range, usize),
+    Protocol { val: String, pos: usize, len: usize },
+    Map(Box<Lazy<'a>>, String),
+    Filter(Box<Lazy<'a>>, String),
+    Take(Box<Lazy<'a>>, usize),
+    Zip(Box<Lazy<'a>>, Box<Lazy<'a>>),
+    Enumerate(Box<Lazy<'a>>, usize),
+    Chan(std::sync::Arc<Channel>),
+}
+
+impl<'a> Lazy<'a> {
+    // Any iterable value as a lazy source; another handle is consumed.
+    fn from_value(val: &str, env: &Env<'a>) -> Lazy<'a> {
+        if let Some(id) = lazy_id(val) {
+            env.take_lazy(id)
+        } else if chan_id(val).is_some() {
+            Lazy::Chan(env.channel(val))
+        } else if let Some(r) = parse_range(val) {
+            Lazy::Range(r, 0)
+        } else if let (Some(len_fn), Some(_)) = (env.protocol_method(val, "length"), env.protocol_method(val, "get")) {
+            let len = call_with_receiver(len_fn, vec![val.to_string()], env);
+            let len = len.parse::<usize>()
+                .unwrap_or_else(|_| fail!("R032", val, len));
+            Lazy::Protocol { val: val.to_string(), pos: 0, len }
+        } else if val.starts_with('[') && val.ends_with(']') {
+            Lazy::Items(parse_array_string(val).into_iter())
+        } else if val.starts_with('{') && val.ends_with('}') {
+            let values: Vec<String> = parse_object_entries(val).into_iter().map(|(_, v)| v).collect();
+            Lazy::Items(values.into_iter())
+        } else if infer_type(val, env).as_deref() == Some("string") {
+            let chars: Vec<String> = graphemes(val).into_iter().map(str::to_string).collect();
+            Lazy::Items(chars.into_iter())
+        } else {
+            fail!("R033", val)
+        }
+    }
+
+    fn next(&mut self, env: &Env<'a>) -> Option<String> {
+        match self {
+            Lazy::Gen(g) => g.next(env),
+            Lazy::Chan(ch) => ch.recv(),
+            Lazy::Items(items) => items.next(),
+            Lazy::Range(r, pos) => {
+                let n = r.get(*pos)?;
+                *pos += 1;
+                Some(n.to_string())
+            }
+            Lazy::Protocol { val, pos, len } => {
+                if *pos >= *len {
+                    return None;
+                }
+                let get_fn = env.protocol_method(val, "get")?;
+                *pos += 1;
+                Some(call_with_receiver(get_fn, vec![val.clone(), (*pos - 1).to_string()], env))
+            }
+            Lazy::Map(src, f) => {
+                let v = src.next(env)?;
+                Some(call_named(f, vec![v], env))
+            }
+            Lazy::Filter(src, f) => loop {
+                let v = src.next(env)?;
+                if truthy(&call_named(f, vec![v.clone()], env)) {
+                    return Some(v);
+                }
+            },
+            Lazy::Take(src, n) => {
+                if *n == 0 {
+                    return None;
+                }
+                *n -= 1;
+                src.next(env)
+            }
+            Lazy::Zip(a, b) => {
+                let x = a.next(env)?;
+                let y = b.next(env)?;
+                Some(serialize_array_vec(&[x, y]))
+            }
+            Lazy::Enumerate(src, i) => {
+                let v = src.next(env)?;
+                *i += 1;
+                Some(serialize_array_vec(&[(*i - 1).to_string(), v]))
+            }
+        }
+    }
+
+    // A dropped sequence: a generator stopped halfway gives back its bindings.
+    fn release(self, env: &Env) {
+        match self {
+            Lazy::Gen(Generator::Running(fiber)) => {
+                for frame in &fiber.frames {
+                    env.release_bindings(&frame.ctx);
+                }
+            }
+            Lazy::Map(src, _) | Lazy::Filter(src, _) | Lazy::Take(src, _) | Lazy::Enumerate(src, _) => src.release(env),
+            Lazy::Zip(a, b) => {
+                a.release(env);
+                b.release(env);
+            }
+            _ => {}
+        }
+    }
+}
+
+// A function containing Y>. Calling it only binds the arguments; the body
+// runs on its own fiber when a value is pulled and stops at the next Y>, so
+// it never needs a thread. The fiber's frame uid is the handle's id, so what
+// the body creates lives as long as the generator.
+enum Generator<'a> {
+    Pending { uid: usize, func: &'a Function, ctx: HashMap<String, (String, Option<String>, bool)> },
+    Running(Fiber<'a>),
+    Done,
+}
+
+impl<'a> Generator<'a> {
+    fn next(&mut self, env: &Env<'a>) -> Option<String> {
+        let mut fiber = match std::mem::replace(self, Generator::Done) {
+            Generator::Pending { uid, func, ctx } => Fiber::new(func, ctx, uid),
+            Generator::Running(fiber) => fiber,
+            Generator::Done => return None,
+        };
+        match drive(&mut fiber, env) {
+            Step::Yield(v) => {
+                *self = Generator::Running(fiber);
+                Some(v)
+            }
+            // R> 只是提前结束生成器
+            Step::Done(_) => None,
+            Step::Suspend(_) => unreachable!("a generator pulled natively never suspends"),
+        }
+    }
+
+    // next 的挂起版本：由任务自身 fiber 上的 L> 拉取时，体内的 sleep/await
+    // 会挂起整个任务而不是阻塞事件循环。resume 是那次等待的结果。
+    fn poll(&mut self, resume: Option<String>, env: &Env<'a>) -> Step {
+        let mut fiber = match std::mem::replace(self, Generator::Done) {
+            Generator::Pending { uid, func, ctx } => Fiber::new(func, ctx, uid),
+            Generator::Running(fiber) => fiber,
+            Generator::Done => return Step::Done(NULL.into()),
+        };
+        if let Some(value) = resume {
+            fiber.push_val(value);
+        }
+        let task = CURRENT_TASK.get();
+        CURRENT_TASK.set(task.map(|(id, _)| (id, DRIVES.get() + 1)));
+        let step = drive(&mut fiber, env);
+        CURRENT_TASK.set(task);
+        if !matches!(step, Step::Done(_)) {
+            *self = Generator::Running(fiber);
+        }
+        step
+    }
+}
+
+fn contains_yield(body: &[Stmt]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Stmt::Yield(_) => true,
+        Stmt::Loop(_, inner) => contains_yield(inner),
+        Stmt::MultiIf(branches) => branches.iter().any(|(_, b)| contains_yield(b)),
+        _ => false,
+    })
+}
+
+// Run a user function with its parameters already bound. Generators return
+// an `<iter#id>` handle instead of running.
+fn run_function<'a>(f: &'a Function, local: HashMap<String, (String, Option<String>, bool)>, env: &Env<'a>) -> String {
+    if contains_yield(&f.body) {
+        let captured: Vec<&str> = local.values().map(|(v, _, _)| v.as_str()).collect();
+        return env.register_lazy(&captured.join(","), |uid| Lazy::Gen(Generator::Pending { uid, func: f, ctx: local }));
+    }
+    exec_function(f, local, env)
+}
+
+// Call a function passed by name, as in `.>map(double)`.
+fn call_named(fname: &str, args: Vec<String>, env: &Env) -> String {
+    match env.fns.get(fname) {
+        Some(f) => call_with_receiver(f, args, env),
+        None => fail!("R015", fname),
+    }
+}
+
+// 协程：每个任务是事件循环线程上的一个 Fiber，在 sleep/await 处停下并把控制权交回，
+// 事件循环一次只运行一个任务，不需要额外的线程。
+enum Clock {
+    Real(std::time::Instant),
+    Mock(u64),
+}
+
+impl Clock {
+    fn now_ms(&self) -> u64 {
+        match self {
+            Clock::Real(start) => start.elapsed().as_millis() as u64,
+            Clock::Mock(t) => *t,
+        }
+    }
+
+    // Wait until `t`; the mock clock jumps there immediately.
+    fn advance_to(&mut self, t: u64) {
+        match self {
+            Clock::Real(start) => {
+                let now = start.elapsed().as_millis() as u64;
+                if t > now {
+                    std::thread::sleep(std::time::Duration::from_millis(t - now));
+                }
+            }
+            Clock::Mock(now) => *now = (*now).max(t),
+        }
+    }
+}
+
+enum TaskState<'a> {
+    Pending { fname: String, args: Vec<String> },
+    Suspended(Fiber<'a>),
+    // 在生成器或回调里等待：它的执行状态在原生栈上，由嵌套的事件循环等它醒来
+    Nested,
+}
+
+// A task's R> value, or the error it stopped with.
+type Outcome = Result<String, String>;
+
+// A failed task without a handle (main, timer callbacks) ends the run; the
+// error is already printed, so this payload unwinds past every try.
+struct RunFailed;
+
+// What a task waits for at sleep/await.
+enum Report {
+    Sleep(u64),
+    Await(usize),
+}
+
+enum TimerKind {
+    Wake(usize),                  // resume a sleeping task
+    After(String),                // run f once
+    Every(String, u64, usize),    // run f every ms until cancelled (timer id)
+}
+
+struct Timer {
+    deadline: u64,
+    seq: usize,
+    kind: TimerKind,
+}
+
+struct Scheduler<'a> {
+    clock: Clock,
+    next_id: usize,
+    ready: std::collections::VecDeque<(usize, Outcome)>, // task, what it resumes with
+    tasks: HashMap<usize, TaskState<'a>>,
+    names: HashMap<usize, String>,       // task -> function it runs, for errors
+    woken: HashMap<usize, Outcome>,      // nested waiters woken while another one runs above them
+    results: HashMap<usize, Outcome>,
+    joinable: std::collections::HashSet<usize>, // tasks from spawn(), which can be awaited
+    unawaited: Vec<usize>,               // failed tasks nobody has awaited yet
+    waiters: HashMap<usize, Vec<usize>>, // awaited task -> waiting tasks
+    timers: Vec<Timer>,
+    cancelled: Vec<usize>,
+}
+
+impl<'a> Scheduler<'a> {
+    fn new(mock_clock: bool) -> Self {
+        Scheduler {
+            clock: if mock_clock { Clock::Mock(0) } else { Clock::Real(std::time::Instant::now()) },
+            next_id: 0,
+            ready: Default::default(),
+            tasks: HashMap::new(),
+            names: HashMap::new(),
+            woken: HashMap::new(),
+            results: HashMap::new(),
+            joinable: Default::default(),
+            unawaited: Vec::new(),
+            waiters: HashMap::new(),
+            timers: Vec::new(),
+            cancelled: Vec::new(),
+        }
+    }
+
+    fn next_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn spawn(&mut self, fname: String, args: Vec<String>) -> usize {
+        let id = self.next_id();
+        self.names.insert(id, fname.clone());
+        self.tasks.insert(id, TaskState::Pending { fname, args });
+        self.ready.push_back((id, Ok(String::new())));
+        id
+    }
+
+    fn add_timer(&mut self, ms: u64, kind: TimerKind) {
+        let deadline = self.clock.now_ms() + ms;
+        let seq = self.next_id();
+        self.timers.push(Timer { deadline, seq, kind });
+    }
+
+    // The next task to resume: ready tasks first, then the earliest timer.
+    fn next_ready(&mut self) -> Option<(usize, Outcome)> {
+        loop {
+            if let Some(next) = self.ready.pop_front() {
+                return Some(next);
+            }
+            let (i, _) = self.timers.iter().enumerate().min_by_key(|(_, t)| (t.deadline, t.seq))?;
+            let timer = self.timers.remove(i);
+            self.clock.advance_to(timer.deadline);
+            match timer.kind {
+                TimerKind::Wake(task) => self.ready.push_back((task, Ok(NULL.into()))),
+                TimerKind::After(f) => {
+                    self.spawn(f, vec![]);
+                }
+                TimerKind::Every(f, ms, id) => {
+                    if !self.cancelled.contains(&id) {
+                        self.spawn(f.clone(), vec![]);
+                        let seq = self.next_id();
+                        self.timers.push(Timer { deadline: timer.deadline + ms, seq, kind: TimerKind::Every(f, ms, id) });
+                    }
+                }
+            }
+        }
+    }
+}
+
+thread_local! {
+    // 当前运行的任务，以及它的根 Fiber 所在的 drive 层数
+    static CURRENT_TASK: std::cell::Cell<Option<(usize, usize)>> = const { std::cell::Cell::new(None) };
+}
+
+fn task_id(val: &str) -> Option<usize> {
+    handle_id(val, "task")
+}
+
+// What sleep(ms) / await(task) waits for, or Err(value) when there is
+// nothing to wait for.
+fn wait_report(name: &str, args: &[String], env: &Env) -> Result<Report, String> {
+    if name == "sleep" {
+        let ms = args.first().and_then(|n| n.parse::<f64>().ok())
+            .unwrap_or_else(|| fail!("R018", "sleep")).max(0.0) as u64;
+        return Ok(Report::Sleep(ms));
+    }
+    let v = args.first().cloned().unwrap_or_default();
+    let Some(id) = task_id(&v) else { return Err(v) };
+    let mut sched = env.scheduler.lock().unwrap();
+    match sched.results.get(&id).cloned() {
+        Some(Ok(value)) => Err(value),
+        // 任务的错误在这里重新抛出
+        Some(Err(message)) => {
+            sched.unawaited.retain(|&t| t != id);
+            drop(sched);
+            panic!("{}", message)
+        }
+        None => Ok(Report::Await(id)),
+    }
+}
+
+// Whether sleep/await here can suspend the running task's own fiber.
+fn at_task_root() -> bool {
+    CURRENT_TASK.get().is_some_and(|(_, level)| level == DRIVES.get())
+}
+
+// sleep/await below the task's own fiber (inside a generator, a callback or
+// an operator): keep running the other tasks until this one is woken.
+// thread() threads have no event loop; there sleep just blocks.
+fn block_on(report: Report, what: &str, env: &Env) -> String {
+    match (CURRENT_TASK.get(), report) {
+        (Some((id, _)), report) => {
+            let mut sched = env.scheduler.lock().unwrap();
+            sched.wait(id, report);
+            sched.tasks.insert(id, TaskState::Nested);
+            drop(sched);
+            run_until(env, Some(id)).expect("nested wait ended without a value")
+                .unwrap_or_else(|message| panic!("{}", message))
+        }
+        (None, Report::Sleep(ms)) => {
+            std::thread::sleep(std::time::Duration::from_millis(ms));
+            NULL.into()
+        }
+        (None, Report::Await(_)) => fail!("R034", what),
+    }
+}
+
+impl<'a> Scheduler<'a> {
+    // Register what the task waits for; it stays off the ready queue until then.
+    fn wait(&mut self, id: usize, report: Report) {
+        match report {
+            Report::Sleep(ms) => self.add_timer(ms, TimerKind::Wake(id)),
+            Report::Await(target) => self.waiters.entry(target).or_default().push(id),
+        }
+    }
+}
+
+fn run_event_loop(env: &Env) {
+    run_until(env, None);
+}
+
+// Run tasks until `target` is woken (a nested wait) or, for the outermost
+// loop, until no task or timer is left. A task that can never be woken is a
+// deadlock (R102), raised at its nested wait or stored as the task's error.
+// A failed task nobody awaited is reported once the loop is done.
+fn run_until(env: &Env, target: Option<usize>) -> Option<Outcome> {
+    loop {
+        let mut sched = env.scheduler.lock().unwrap();
+        if let Some(value) = target.and_then(|t| sched.woken.remove(&t)) {
+            sched.tasks.remove(&target?);
+            return Some(value);
+        }
+        let Some((id, value)) = sched.next_ready() else {
+            // 没有就绪的任务也没有定时器：等待者再也不会被唤醒
+            let Some(stuck) = target.or_else(|| sched.tasks.keys().min().copied()) else {
+                let failed = sched.unawaited.first().and_then(|t| sched.results.get(t).cloned());
+                drop(sched);
+                if let Some(Err(message)) = failed {
+                    panic!("{}", message);
+                }
+                return None;
+            };
+            let state = sched.tasks.remove(&stuck);
+            for list in sched.waiters.values_mut() {
+                list.retain(|&w| w != stuck);
+            }
+            let name = sched.names.get(&stuck).cloned().unwrap_or_default();
+            drop(sched);
+            if target.is_some() {
+                fail!("R102", name);
+            }
+            if let Some(TaskState::Suspended(mut fiber)) = state {
+                fiber.abandon(env);
+            }
+            finish_task(stuck, Err(messages::error("R102", None, &[name])), env);
+            continue;
+        };
+        let fiber = match sched.tasks.remove(&id).expect("unknown task") {
+            TaskState::Nested => {
+                if Some(id) == target {
+                    return Some(value);
+                }
+                // 它在原生栈上更靠下，等上面的嵌套等待结束后再交给它
+                sched.woken.insert(id, value);
+                sched.tasks.insert(id, TaskState::Nested);
+                continue;
+            }
+            TaskState::Pending { fname, args } => {
+                drop(sched);
+                let f = *env.fns.get(&fname).unwrap_or_else(|| fail!("R015", fname));
+                let local = bind_args(f, args, env);
+                // 生成器任务：直接以它的 iter 作为结果
+                if contains_yield(&f.body) {
+                    let result = messages::caught(|| run_function(f, local, env));
+                    finish_task(id, result, env);
+                    continue;
+                }
+                Fiber::new(f, local, env.next_id())
+            }
+            TaskState::Suspended(mut fiber) => {
+                drop(sched);
+                match value {
+                    Ok(value) => fiber.push_val(value),
+                    // 它等的任务失败了：错误在它的 await 处抛出，没有 try 能接住
+                    Err(message) => {
+                        fiber.abandon(env);
+                        finish_task(id, Err(message), env);
+                        continue;
+                    }
+                }
+                fiber
+            }
+        };
+        run_task(id, fiber, env);
+    }
+}
+
+// Drive a task's fiber until it finishes or waits again.
+fn run_task<'a>(id: usize, mut fiber: Fiber<'a>, env: &Env<'a>) {
+    let outer = (CURRENT_TASK.get(), DEPTH.get(), OWNER.get());
+    CURRENT_TASK.set(Some((id, DRIVES.get() + 1)));
+    DEPTH.set(0);
+    OWNER.set(0);
+    // 任务的错误留在任务上，不会穿过宿主栈上别的任务的 try
+    let step = messages::caught(|| drive(&mut fiber, env));
+    CURRENT_TASK.set(outer.0);
+    DEPTH.set(outer.1);
+    OWNER.set(outer.2);
+    match step {
+        Ok(Step::Done(result)) => finish_task(id, Ok(result), env),
+        Ok(Step::Suspend(report)) => {
+            let mut sched = env.scheduler.lock().unwrap();
+            sched.wait(id, report);
+            sched.tasks.insert(id, TaskState::Suspended(fiber));
+        }
+        Ok(Step::Yield(_)) => unreachable!("Y> outside of a generator"),
+        Err(message) => finish_task(id, Err(message), env),
+    }
+}
+
+// Wake the task's waiters with its outcome. An error waits for an await to
+// raise it; a task nobody can await (main, timer callbacks) ends the run.
+fn finish_task(id: usize, result: Outcome, env: &Env) {
+    if let Ok(value) = &result {
+        env.escape_lazies(value);
+    }
+    let mut sched = env.scheduler.lock().unwrap();
+    if let Err(message) = &result && !sched.joinable.contains(&id) {
+        let message = message.clone();
+        drop(sched);
+        eprintln!("{}", message);
+        std::panic::resume_unwind(Box::new(RunFailed));
+    }
+    let waiters = sched.waiters.remove(&id).unwrap_or_default();
+    if result.is_err() && waiters.is_empty() {
+        sched.unawaited.push(id);
+    }
+    for waiter in waiters {
+        sched.ready.push_back((waiter, result.clone()));
+    }
+    sched.results.insert(id, result);
+}
+
+// Interpreter threads get the main thread's stack size for deep recursion.
+const INTERPRETER_STACK: usize = 8 * 1024 * 1024;
+
+// 系统线程都是 execute_file 作用域里的线程：Env 不必是 'static，
+// 程序在所有线程退出之后才结束。
+type Job<'a> = Box<dyn FnOnce(&Env<'a>) + Send + 'a>;
+
+enum Spawn<'a> {
+    Job(Job<'a>),
+    Exited,
+    Finished(std::thread::Result<()>), // the main interpreter thread
+}
+
+// Start `main_job` and every thread it asks for; returns once all of them
+// have exited, with the main thread's result.
+fn run_threads<'a>(env: &Env<'a>, main_job: Job<'a>) -> std::thread::Result<()> {
+    let (spawner, jobs) = mpsc::channel();
+    *env.spawner.lock().unwrap() = Some(spawner.clone());
+    let result = std::thread::scope(|scope| {
+        let start = |job: Job<'a>, is_main: bool| {
+            let done = spawner.clone();
+            std::thread::Builder::new()
+                .stack_size(INTERPRETER_STACK)
+                .spawn_scoped(scope, move || {
+                    reserve_native_stack(INTERPRETER_STACK);
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| job(env)));
+                    let _ = done.send(if is_main { Spawn::Finished(result) } else { Spawn::Exited });
+                })
+                .expect("failed to spawn interpreter thread");
+        };
+        start(main_job, true);
+        let mut live = 1;
+        let mut main_result = None;
+        while live > 0 {
+            match jobs.recv().expect("spawner dropped") {
+                Spawn::Job(job) => {
+                    live += 1;
+                    start(job, false);
+                }
+                Spawn::Exited => live -= 1,
+                Spawn::Finished(result) => {
+                    live -= 1;
+                    // main 已结束：关闭所有通道，让还在 recv 的线程退出
+                    for ch in env.channels.lock().unwrap().values() {
+                        ch.close();
+                    }
+                    main_result = Some(result);
+                }
+            }
+        }
+        main_result.expect("main thread did not finish")
+    });
+    *env.spawner.lock().unwrap() = None;
+    result
+}
+
+// Iteration entries for L>: (index or key, item). Ranges, iters,
+// channels and protocol types stay lazy.
+enum Entries<'a> {
+    Seq(Lazy<'a>, usize),
+    Pairs(std::vec::IntoIter<(String, String)>),
+}
+
+impl<'a> Entries<'a> {
+    fn next(&mut self, env: &Env<'a>) -> Option<(String, String)> {
+        match self {
+            Entries::Seq(lazy, i) => {
+                let el = lazy.next(env)?;
+                *i += 1;
+                Some(((*i - 1).to_string(), el))
+            }
+            Entries::Pairs(pairs) => pairs.next(),
+        }
+    }
+
+    // next for a loop on a task's own fiber: a generator source may suspend
+    // the task (Err) instead of blocking the event loop.
+    fn poll(&mut self, resume: Option<String>, env: &Env<'a>) -> Result<Option<(String, String)>, Report> {
+        if let Entries::Seq(Lazy::Gen(g), i) = self && at_task_root() {
+            return match g.poll(resume, env) {
+                Step::Yield(el) => {
+                    *i += 1;
+                    Ok(Some(((*i - 1).to_string(), el)))
+                }
+                Step::Done(_) => Ok(None),
+                Step::Suspend(report) => Err(report),
+            };
+        }
+        Ok(self.next(env))
+    }
+}
+
+fn foreach_entries<'a>(list_val: &str, env: &Env<'a>) -> Entries<'a> {
+    let indexed = |items: Vec<String>| items.into_iter().enumerate().map(|(i, el)| (i.to_string(), el)).collect::<Vec<_>>();
+    if lazy_id(list_val).is_some() || chan_id(list_val).is_some() || parse_range(list_val).is_some()
+        || (env.protocol_method(list_val, "length").is_some() && env.protocol_method(list_val, "get").is_some()) {
+        Entries::Seq(Lazy::from_value(list_val, env), 0)
+    } else if list_val.starts_with('[') && list_val.ends_with(']') {
+        Entries::Pairs(indexed(parse_array_string(list_val)).into_iter())
+    } else if list_val.starts_with('{') && list_val.ends_with('}') {
+        Entries::Pairs(parse_object_entries(list_val).into_iter())
+    } else if infer_type(list_val, env).as_deref() == Some("string") {
+        let chars = graphemes(list_val).into_iter().map(str::to_string).collect();
+        Entries::Pairs(indexed(chars).into_iter())
+    } else {
+        fail!("R035", list_val);
+    }
+}
+
+// 线程与通道：thread(f, args...) 在系统线程上运行函数，chan() 在线程间传值。
+// 与协程不同，这些线程真正并行；值都是字符串，Env 本身是 Sync 的。
+struct Channel {
+    state: Mutex<(std::collections::VecDeque<String>, bool)>, // queue, closed
+    ready: std::sync::Condvar,
+}
+
+impl Channel {
+    fn send(&self, val: String) {
+        let mut state = self.state.lock().unwrap();
+        if state.1 {
+            drop(state);
+            fail!("R036");
+        }
+        state.0.push_back(val);
+        self.ready.notify_one();
+    }
+
+    // Blocks until a value arrives; None once the channel is closed and drained.
+    fn recv(&self) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(v) = state.0.pop_front() {
+                return Some(v);
+            }
+            if state.1 {
+                return None;
+            }
+            state = self.ready.wait(state).unwrap();
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().1 = true;
+        self.ready.notify_all();
+    }
+}
+
+fn chan_id(val: &str) -> Option<usize> {
+    handle_id(val, "chan")
+}
+
+fn thread_id(val: &str) -> Option<usize> {
+    handle_id(val, "thread")
+}
+
+thread_local! {
+    // 并行循环的一次迭代中，print 写入这里而不是直接输出
+    static OUTPUT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// print: to the enclosing parallel iteration's buffer, or stdout.
+fn emit(line: &str) {
+    emit_raw(&format!("{}\n", line));
+}
+
+fn emit_raw(text: &str) {
+    let text = display(text);
+    OUTPUT.with(|o| match o.borrow_mut().as_mut() {
+        Some(buf) => buf.push_str(&text),
+        None => print!("{}", text),
+    })
+}
+
+// 输出时去掉内部值的标记：null 显示为 `null`
+fn display(val: &str) -> String {
+    val.replace(VALUE_TAG, "")
+}
+
+fn capture_output(f: impl FnOnce()) -> String {
+    let outer = OUTPUT.with(|o| o.borrow_mut().replace(String::new()));
+    f();
+    OUTPUT.with(|o| std::mem::replace(&mut *o.borrow_mut(), outer)).unwrap_or_default()
+}
+
+// Run job(0..n) on a pool of scoped threads; results come back in index order.
+fn run_parallel<T: Send>(n: usize, job: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let workers = std::thread::available_parallelism().map_or(1, |p| p.get()).min(n);
+    let next = AtomicUsize::new(0);
+    let owner = OWNER.get();
+    let mut results: Vec<(usize, T)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                std::thread::Builder::new()
+                    .stack_size(INTERPRETER_STACK)
+                    .spawn_scoped(scope, || {
+                        reserve_native_stack(INTERPRETER_STACK);
+                        OWNER.set(owner);
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= n {
+                                break done;
+                            }
+                            done.push((i, job(i)));
+                        }
+                    })
+                    .expect("failed to spawn worker thread")
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|payload| std::panic::resume_unwind(payload)))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+pub enum ExecResult {
+    None,
+    Return(String),
+    Break,
+}
+
+// The single missing value: returns without R>, missing arguments, missing
+// keys and out-of-bounds reads all produce it. It carries VALUE_TAG, so no
+// user string (not even "null") can be mistaken for it; print shows `null`.
+const NULL: &str = "\u{FDD0}null";
+
+// 宽松模式下的静默回退值；--strict 或文件头 `//! strict` 下改为带源码位置的运行时错误
+fn fallback(site: usize, code: &str, args: &[String], value: &str, env: &Env) -> String {
+    if env.options.strict {
+        panic!("{}", messages::error(code, Some(site_position(site)), args));
+    }
+    value.to_string()
+}
+
+// 唯一的真值规则，供 !、if、L>(cond)、&&、|| 与链式比较共用：
+// false、null、数值 0、空字符串与空数组/对象为假，其余为真。
+// 值以文本保存，所以字符串 "false" 与 "0"、"0.0" 这类零值文本同样为假；不做 trim，" " 为真
+fn truthy(v: &str) -> bool {
+    !(v.is_empty()
+        || v == "false"
+        || v == NULL
+        || v == "[]"
+        || v == "{}"
+        || v.parse::<f64>().is_ok_and(|n| n == 0.0))
+}
+
+// null、true、false 是字面量，不能被同名变量遮蔽
+fn check_declarable(name: &str) {
+    if matches!(name, "null" | "true" | "false") {
+        fail!("R098", name);
+    }
+}
+
+fn bool_value(b: bool) -> String {
+    if b { "true" } else { "false" }.to_string()
+}
+
+// Operand of + - * / < >: bools count as 1 and 0, anything else
+// non-numeric as 0 (an error under --strict).
+fn as_number(v: &str, site: usize, env: &Env) -> f64 {
+    match v {
+        "true" => 1.0,
+        "false" => 0.0,
+        _ => v.parse::<f64>().unwrap_or_else(|_| {
+            fallback(site, "R089", &[v.to_string()], "0", env);
+            0.0
+        }),
+    }
+}
+
+// 逐条执行循环体，遇到 B> 或 R> 立即停止并交给循环处理
+fn run_loop_body(
+    body: &[Stmt],
+    ctx: &mut HashMap<String, (String, Option<String>, bool)>,
+    env: &Env,
+) -> ExecResult {
+    for stmt in body {
+        let result = exec_stmt(stmt, ctx, env);
+        if !matches!(result, ExecResult::None) {
+            return result;
+        }
+    }
+    ExecResult::None
+}
+
+const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+thread_local! {
+    // 当前线程上嵌套的 FLYUX 调用层数（所有 Fiber 的帧合计）
+    static DEPTH: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    // 原生栈可用到的最低地址；0 表示不检查
+    static STACK_LIMIT: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    // 正在执行的帧的 uid，新建的 <iter#id> 归它所有；0 表示整个运行
+    static OWNER: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    // 当前线程上嵌套的 drive 层数
+    static DRIVES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+// 调用栈在堆上：一次 FLYUX 调用是 Fiber 里的一个 Frame，函数体拆成 Work 逐项执行。
+// 调用用户函数时压入新帧、R> 时弹出，原生栈不随递归增长，深度只受 --max-depth 限制。
+// 不含用户调用的语句与表达式仍直接交给 exec_stmt / eval_expr。
+struct Fiber<'a> {
+    frames: Vec<Frame<'a>>,
+    outer: usize,         // 驱动它的一方的 OWNER
+}
+
+struct Frame<'a> {
+    uid: usize,           // owner id of the handles it creates
+    func: &'a Function,   // 尾调用后是被调函数
+    ctx: HashMap<String, (String, Option<String>, bool)>,
+    work: Vec<Work<'a>>,  // 待执行的步骤，栈顶先执行
+    vals: Vec<String>,    // 已求出的值
+}
+
+// One pending step of a frame.
+enum Work<'a> {
+    Block(&'a [Stmt], usize),        // statements from the index on
+    Stmt(&'a Stmt),
+    Eval(&'a Expr),                  // push the expression's value
+    Finish(&'a Expr, usize),         // its n operands are on vals: compute the node
+    Invoke(&'a Expr),                // the call's arguments are on vals
+    Then(&'a Expr),                  // left side of && || ?? or receiver of ?.> is on vals
+    Truth,                           // right side of && ||
+    Apply(&'a Stmt),                 // right-hand side is on vals: run the statement with it
+    Discard,                         // a call used as a statement
+    Return,
+    TailCall(&'a Function, usize, usize), // R>f(...): its n arguments are on vals; call site
+    Yield,
+    Branch(&'a [(Option<Expr>, Vec<Stmt>)], usize), // try branch i of an if chain
+    Test(&'a [(Option<Expr>, Vec<Stmt>)], usize),   // condition of branch i is on vals
+    Loop(&'a Stmt, LoopState<'a>),   // a running loop; B> unwinds to here
+    LoopValue(&'a Stmt),             // the loop's count, iterable or condition is on vals
+}
+
+enum LoopState<'a> {
+    Start,
+    Check,                           // L>(init; cond; step): test cond next
+    Body,                            // an iteration just finished
+    Times(usize, usize),             // next counter, count
+    Each(Entries<'a>, &'static str), // entries, key type
+    Resume(Entries<'a>, &'static str), // the source suspended the task; its wait's value is on vals
+}
+
+// Why a fiber stopped running.
+enum Step {
+    Done(String),
+    Yield(String),
+    Suspend(Report),   // a task's own fiber at sleep/await
+}
+
+impl<'a> Fiber<'a> {
+    fn new(f: &'a Function, local: HashMap<String, (String, Option<String>, bool)>, uid: usize) -> Self {
+        let base = Frame { uid, func: f, ctx: local, work: vec![Work::Block(&f.body, 0)], vals: Vec::new() };
+        Fiber { frames: vec![base], outer: 0 }
+    }
+
+    // Whose handles are created now: the top frame's, or the driver's once none is left.
+    fn owner(&self) -> usize {
+        self.frames.last().map_or(self.outer, |frame| frame.uid)
+    }
+
+    fn top(&mut self) -> &mut Frame<'a> {
+        self.frames.last_mut().expect("fiber has no frame")
+    }
+
+    fn push(&mut self, work: Work<'a>) {
+        self.top().work.push(work);
+    }
+
+    // Drop every frame of a fiber that will not run again, with its bindings and handles.
+    fn abandon(&mut self, env: &Env) {
+        for frame in self.frames.drain(..) {
+            env.release_bindings(&frame.ctx);
+            env.release_lazies(frame.uid, "", self.outer);
+        }
+    }
+
+    fn push_val(&mut self, val: String) {
+        self.top().vals.push(val);
+    }
+
+    fn pop_val(&mut self) -> String {
+        self.top().vals.pop().expect("missing operand")
+    }
+
+    fn pop_vals(&mut self, n: usize) -> Vec<String> {
+        let vals = &mut self.top().vals;
+        vals.split_off(vals.len() - n)
+    }
+
+    // Evaluate `exprs` left to right onto vals.
+    fn eval_all(&mut self, exprs: impl DoubleEndedIterator<Item = &'a Expr>) {
+        for e in exprs.rev() {
+            self.push(Work::Eval(e));
+        }
+    }
+
+    // A new frame for `f`; going past --max-depth is the R038 runtime error.
+    fn enter(&mut self, f: &'a Function, local: HashMap<String, (String, Option<String>, bool)>, site: Option<usize>, env: &Env) {
+        let depth = DEPTH.get() + 1;
+        let max_depth = env.options.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        if depth > max_depth {
+            panic!("{}", messages::error("R038", site.map(site_position), &[max_depth.to_string(), f.name.clone()]));
+        }
+        DEPTH.set(depth);
+        let uid = env.next_id();
+        OWNER.set(uid);
+        self.frames.push(Frame { uid, func: f, ctx: local, work: vec![Work::Block(&f.body, 0)], vals: Vec::new() });
+    }
+
+    // Call a user function; a generator only hands back its iter.
+    fn call(&mut self, f: &'a Function, local: HashMap<String, (String, Option<String>, bool)>, site: usize, env: &Env<'a>) {
+        if contains_yield(&f.body) {
+            let handle = run_function(f, local, env);
+            self.push_val(handle);
+        } else {
+            self.enter(f, local, Some(site), env);
+        }
+    }
+
+    // The top frame returns `val`; the fiber's result once no frame is left.
+    fn finish(&mut self, val: String, env: &Env) -> Option<String> {
+        let frame = self.frames.pop().expect("fiber has no frame");
+        env.release_bindings(&frame.ctx);
+        DEPTH.set(DEPTH.get() - 1);
+        OWNER.set(self.owner());
+        env.release_lazies(frame.uid, &val, OWNER.get());
+        match self.frames.last_mut() {
+            Some(caller) => {
+                caller.vals.push(val);
+                None
+            }
+            None => Some(val),
+        }
+    }
+
+    // B>: drop the rest of the innermost loop of the top frame.
+    fn unwind_break(&mut self) {
+        let frame = self.top();
+        while let Some(work) = frame.work.pop() {
+            if matches!(work, Work::Loop(..)) {
+                return;
+            }
+        }
+        fail!("R037", frame.func.name);
+    }
+
+    fn run(&mut self, env: &Env<'a>) -> Step {
+        loop {
+            let Some(work) = self.top().work.pop() else {
+                // 函数体执行完而没有 R>
+                match self.finish(NULL.into(), env) {
+                    Some(v) => return Step::Done(v),
+                    None => continue,
+                }
+            };
+            let returned = match work {
+                Work::Block(stmts, i) => {
+                    if let Some(stmt) = stmts.get(i) {
+                        self.push(Work::Block(stmts, i + 1));
+                        self.push(Work::Stmt(stmt));
+                    }
+                    None
+                }
+                Work::Stmt(stmt) => self.start_stmt(stmt, env),
+                Work::Eval(expr) => {
+                    self.start_expr(expr, env);
+                    None
+                }
+                Work::Finish(expr, n) => {
+                    let node = with_operands(expr, self.pop_vals(n));
+                    let frame = self.top();
+                    let val = eval_expr(&node, &mut frame.ctx, env);
+                    frame.vals.push(val);
+                    None
+                }
+                Work::Invoke(expr) => {
+                    if let Some(report) = self.invoke(expr, env) {
+                        return Step::Suspend(report);
+                    }
+                    None
+                }
+                Work::Then(expr) => {
+                    let left = self.pop_val();
+                    match expr {
+                        Expr::Logical(op, _, right) => match (op.as_str(), truthy(&left)) {
+                            ("&&", false) => self.push_val(bool_value(false)),
+                            ("||", true) => self.push_val(bool_value(true)),
+                            ("&&" | "||", _) => {
+                                self.push(Work::Truth);
+                                self.push(Work::Eval(right));
+                            }
+                            _ => fail!("R075", op),
+                        },
+                        Expr::Coalesce(_, right) if left == NULL => self.push(Work::Eval(right)),
+                        Expr::SafeMethodCall { args, .. } if left != NULL => {
+                            self.push_val(left);
+                            self.push(Work::Invoke(expr));
+                            self.eval_all(args.iter());
+                        }
+                        _ => self.push_val(left),
+                    }
+                    None
+                }
+                Work::Truth => {
+                    let v = self.pop_val();
+                    self.push_val(bool_value(truthy(&v)));
+                    None
+                }
+                Work::Apply(stmt) => {
+                    let stmt = match stmt {
+                        Stmt::Expr(Expr::Call(name, args, site)) => {
+                            let args = self.pop_vals(args.len()).into_iter().map(Expr::Str).collect();
+                            Stmt::Expr(Expr::Call(name.clone(), args, *site))
+                        }
+                        Stmt::ConstDecl(name, typ, _) => Stmt::ConstDecl(name.clone(), typ.clone(), Expr::Str(self.pop_val())),
+                        Stmt::VarDecl(name, typ, _) => Stmt::VarDecl(name.clone(), typ.clone(), Expr::Str(self.pop_val())),
+                        Stmt::ForceAssign(name, typ, _) => Stmt::ForceAssign(name.clone(), typ.clone(), Expr::Str(self.pop_val())),
+                        Stmt::Assign(name, _) => Stmt::Assign(name.clone(), Expr::Str(self.pop_val())),
+                        Stmt::PropAssign(lhs, _) => Stmt::PropAssign(lhs.clone(), Expr::Str(self.pop_val())),
+                        _ => unreachable!("statement without a right-hand side"),
+                    };
+                    self.native_stmt(&stmt, env)
+                }
+                Work::Discard => {
+                    self.pop_val();
+                    None
+                }
+                Work::Return => {
+                    let v = self.pop_val();
+                    self.finish(v, env)
+                }
+                Work::TailCall(g, n, site) => {
+                    // 尾调用复用当前帧，不增加深度
+                    let args = self.pop_vals(n);
+                    let passed = args.join(",");
+                    let ctx = bind_call(g, args, site, env);
+                    let uid = env.next_id();
+                    let frame = self.top();
+                    env.release_bindings(&frame.ctx);
+                    // 实参里的 <iter#id> 交给新的一轮调用，其余随旧的一轮释放
+                    env.release_lazies(frame.uid, &passed, uid);
+                    frame.uid = uid;
+                    OWNER.set(uid);
+                    frame.ctx = ctx;
+                    frame.func = g;
+                    frame.work = vec![Work::Block(&g.body, 0)];
+                    None
+                }
+                Work::Yield => return Step::Yield(self.pop_val()),
+                Work::Branch(branches, i) => {
+                    match branches.get(i) {
+                        Some((None, body)) => self.push(Work::Block(body, 0)),
+                        Some((Some(cond), _)) => {
+                            self.push(Work::Test(branches, i));
+                            self.push(Work::Eval(cond));
+                        }
+                        None => {}
+                    }
+                    None
+                }
+                Work::Test(branches, i) => {
+                    if truthy(&self.pop_val()) {
+                        self.push(Work::Block(&branches[i].1, 0));
+                    } else {
+                        self.push(Work::Branch(branches, i + 1));
+                    }
+                    None
+                }
+                Work::Loop(stmt, state) => {
+                    if let Some(report) = self.loop_step(stmt, state, env) {
+                        return Step::Suspend(report);
+                    }
+                    None
+                }
+                Work::LoopValue(stmt) => {
+                    let v = self.pop_val();
+                    self.loop_value(stmt, v, env);
+                    None
+                }
+            };
+            if let Some(v) = returned {
+                return Step::Done(v);
+            }
+        }
+    }
+
+    // Run a statement that makes no user calls directly.
+    fn native_stmt(&mut self, stmt: &Stmt, env: &Env) -> Option<String> {
+        let frame = self.top();
+        match exec_stmt(stmt, &mut frame.ctx, env) {
+            ExecResult::None => None,
+            ExecResult::Return(v) => self.finish(v, env),
+            ExecResult::Break => {
+                self.unwind_break();
+                None
+            }
+        }
+    }
+
+    fn start_stmt(&mut self, stmt: &'a Stmt, env: &Env<'a>) -> Option<String> {
+        if !needs_fiber(stmt, env) {
+            return self.native_stmt(stmt, env);
+        }
+        match stmt {
+            Stmt::ConstDecl(_, _, e) | Stmt::VarDecl(_, _, e) | Stmt::ForceAssign(_, _, e)
+            | Stmt::Assign(_, e) | Stmt::PropAssign(_, e) => {
+                self.push(Work::Apply(stmt));
+                self.push(Work::Eval(e));
+            }
+            Stmt::Expr(Expr::Call(name, args, _)) if name == "print" => {
+                self.push(Work::Apply(stmt));
+                self.eval_all(args.iter());
+            }
+            Stmt::Expr(call) => {
+                // 作为语句调用时丢弃返回值
+                self.push(Work::Discard);
+                self.push(Work::Eval(call));
+            }
+            Stmt::Return(Expr::Call(name, args, site))
+                if env.fns.get(name).is_some_and(|f| !contains_yield(&f.body)) => {
+                self.push(Work::TailCall(env.fns[name], args.len(), *site));
+                self.eval_all(args.iter());
+            }
+            Stmt::Return(e) => {
+                self.push(Work::Return);
+                self.push(Work::Eval(e));
+            }
+            Stmt::Yield(e) => {
+                self.push(Work::Yield);
+                self.push(Work::Eval(e));
+            }
+            Stmt::MultiIf(branches) => self.push(Work::Branch(branches, 0)),
+            Stmt::Loop(..) => self.push(Work::Loop(stmt, LoopState::Start)),
+            _ => return self.native_stmt(stmt, env),
+        }
+        None
+    }
+
+    fn start_expr(&mut self, expr: &'a Expr, env: &Env<'a>) {
+        if !calls_user(expr, env) {
+            let frame = self.top();
+            let val = eval_expr(expr, &mut frame.ctx, env);
+            frame.vals.push(val);
+            return;
+        }
+        match expr {
+            Expr::Call(_, args, _) => {
+                self.push(Work::Invoke(expr));
+                self.eval_all(args.iter());
+            }
+            Expr::MethodCall { target, args, .. } => {
+                self.push(Work::Invoke(expr));
+                self.eval_all(std::iter::once(target.as_ref()).chain(args));
+            }
+            Expr::SafeMethodCall { target: left, .. } | Expr::Logical(_, left, _) | Expr::Coalesce(left, _) => {
+                self.push(Work::Then(expr));
+                self.push(Work::Eval(left));
+            }
+            Expr::Input(_) => {
+                let frame = self.top();
+                let val = eval_expr(expr, &mut frame.ctx, env);
+                frame.vals.push(val);
+            }
+            _ => {
+                let ops = operands(expr);
+                self.push(Work::Finish(expr, ops.len()));
+                self.eval_all(ops.into_iter());
+            }
+        }
+    }
+
+    // A call whose arguments are on vals; Some when the task has to wait.
+    fn invoke(&mut self, expr: &'a Expr, env: &Env<'a>) -> Option<Report> {
+        match expr {
+            Expr::Call(name, args, site) => match env.fns.get(name) {
+                Some(f) => {
+                    let passed = self.pop_vals(args.len());
+                    self.call(f, bind_call(f, passed, *site, env), *site, env);
+                }
+                None => {
+                    let passed = self.pop_vals(args.len());
+                    return self.builtin(name, passed, env);
+                }
+            },
+            Expr::MethodCall { name, args, site, .. } | Expr::SafeMethodCall { name, args, site, .. } => {
+                let passed = self.pop_vals(args.len() + 1);
+                let recv_type = infer_type(&passed[0], env).unwrap_or_else(|| "string".into());
+                match env.resolve_method(*site, &recv_type, name) {
+                    Some(Dispatch::Builtin) => return self.builtin(name, passed, env),
+                    Some(Dispatch::Method(m)) | Some(Dispatch::Free(m)) => self.call(m, bind_args(m, passed, env), *site, env),
+                    None => fail!("R065", name),
+                }
+            }
+            _ => unreachable!("not a call"),
+        }
+        None
+    }
+
+    // sleep/await on a task's own fiber suspend it; other builtins just run.
+    fn builtin(&mut self, name: &str, passed: Vec<String>, env: &Env<'a>) -> Option<Report> {
+        if SUSPENDING.contains(&name) && at_task_root() {
+            match wait_report(name, &passed, env) {
+                Ok(report) => return Some(report),
+                Err(value) => self.push_val(value),
+            }
+        } else {
+            let val = call_builtin(name, passed, env).unwrap_or_else(|| NULL.into());
+            self.push_val(val);
+        }
+        None
+    }
+
+    // A loop marker came back to the top: start the next iteration or stop.
+    fn loop_step(&mut self, stmt: &'a Stmt, state: LoopState<'a>, env: &Env<'a>) -> Option<Report> {
+        let Stmt::Loop(kind, body) = stmt else { unreachable!("not a loop") };
+        match (kind, state) {
+            (LoopKind::Times(e, _) | LoopKind::ForEach(_, _, e), LoopState::Start)
+            | (LoopKind::While(e) | LoopKind::Until(e), LoopState::Start | LoopState::Body)
+            | (LoopKind::DoWhile(e) | LoopKind::DoUntil(e), LoopState::Body)
+            | (LoopKind::For(_, e, _), LoopState::Check) => {
+                self.push(Work::LoopValue(stmt));
+                self.push(Work::Eval(e));
+            }
+            (LoopKind::Infinite | LoopKind::DoWhile(_) | LoopKind::DoUntil(_), _) => {
+                self.push(Work::Loop(stmt, LoopState::Body));
+                self.push(Work::Block(body, 0));
+            }
+            (LoopKind::For(init, _, _), LoopState::Start) => {
+                self.push(Work::Loop(stmt, LoopState::Check));
+                self.push(Work::Stmt(init));
+            }
+            (LoopKind::For(_, _, step), _) => {
+                self.push(Work::Loop(stmt, LoopState::Check));
+                self.push(Work::Stmt(step));
+            }
+            (LoopKind::Times(_, counter), LoopState::Times(i, count)) => {
+                if i < count {
+                    let counter = counter.as_deref().unwrap_or("_");
+                    let frame = self.top();
+                    frame.ctx.insert(counter.to_string(), (i.to_string(), Some("int".to_string()), false));
+                    env.invalidate(counter, &frame.ctx);
+                    self.push(Work::Loop(stmt, LoopState::Times(i + 1, count)));
+                    self.push(Work::Block(body, 0));
+                }
+            }
+            (LoopKind::ForEach(index_var, var, _), state @ (LoopState::Each(..) | LoopState::Resume(..))) => {
+                let (mut entries, key_type, resume) = match state {
+                    LoopState::Each(entries, key_type) => (entries, key_type, None),
+                    LoopState::Resume(entries, key_type) => (entries, key_type, Some(self.pop_val())),
+                    _ => unreachable!(),
+                };
+                let next = match entries.poll(resume, env) {
+                    Ok(next) => next,
+                    Err(report) => {
+                        self.push(Work::Loop(stmt, LoopState::Resume(entries, key_type)));
+                        return Some(report);
+                    }
+                };
+                if let Some((key, el)) = next {
+                    let frame = self.top();
+                    if let Some(index_var) = index_var {
+                        frame.ctx.insert(index_var.clone(), (key, Some(key_type.to_string()), false));
+                        env.invalidate(index_var, &frame.ctx);
+                    }
+                    let item_type = infer_type(&el, env);
+                    frame.ctx.insert(var.clone(), (el, item_type, false));
+                    env.invalidate(var, &frame.ctx);
+                    self.push(Work::Loop(stmt, LoopState::Each(entries, key_type)));
+                    self.push(Work::Block(body, 0));
+                }
+            }
+            _ => unreachable!("loop state does not match its kind"),
+        }
+        None
+    }
+
+    // The loop's count, iterable or condition has been evaluated.
+    fn loop_value(&mut self, stmt: &'a Stmt, val: String, env: &Env<'a>) {
+        let Stmt::Loop(kind, body) = stmt else { unreachable!("not a loop") };
+        let go_on = match kind {
+            LoopKind::Times(expr, _) => {
+                let count = val.parse::<usize>()
+                    .unwrap_or_else(|_| fail!("R047", format!("{:?}", expr)));
+                return self.push(Work::Loop(stmt, LoopState::Times(0, count)));
+            }
+            LoopKind::ForEach(..) => {
+                let key_type = if val.starts_with('{') { "string" } else { "int" };
+                let entries = foreach_entries(&val, env);
+                return self.push(Work::Loop(stmt, LoopState::Each(entries, key_type)));
+            }
+            LoopKind::While(_) | LoopKind::DoWhile(_) | LoopKind::For(..) => truthy(&val),
+            LoopKind::Until(_) | LoopKind::DoUntil(_) => !truthy(&val),
+            LoopKind::Infinite | LoopKind::ParallelForEach(..) => unreachable!("loop without a value"),
+        };
+        if go_on {
+            self.push(Work::Loop(stmt, LoopState::Body));
+            self.push(Work::Block(body, 0));
+        }
+    }
+}
+
+// Builtins that make a task wait.
+const SUSPENDING: [&str; 2] = ["sleep", "await"];
+
+// Whether an expression calls a user function (directly or through `.>`) or
+// may suspend, so that the fiber has to take it apart instead of eval_expr.
+fn calls_user(expr: &Expr, env: &Env) -> bool {
+    match expr {
+        Expr::Call(name, args, _) => {
+            env.fns.contains_key(name) || SUSPENDING.contains(&name.as_str()) || args.iter().any(|a| calls_user(a, env))
+        }
+        Expr::MethodCall { target, name, args, .. } | Expr::SafeMethodCall { target, name, args, .. } => {
+            !BUILTIN_METHODS.contains(&name.as_str())
+                || SUSPENDING.contains(&name.as_str())
+                || calls_user(target, env)
+                || args.iter().any(|a| calls_user(a, env))
+        }
+        Expr::Number(_) | Expr::Str(_) | Expr::Ident(_) | Expr::PostfixIncrement(_) | Expr::PostfixDecrement(_) => false,
+        Expr::Input(args) | Expr::Array(args) | Expr::Variant(_, _, args) => args.iter().any(|a| calls_user(a, env)),
+        Expr::Binary(l, _, r, _) | Expr::Logical(_, l, r) | Expr::Index(l, r, _) | Expr::Coalesce(l, r) => {
+            calls_user(l, env) || calls_user(r, env)
+        }
+        Expr::Not(inner) | Expr::Access(inner, _, _) | Expr::SafeAccess(inner, _, _) | Expr::Is(inner, _, _, _) => {
+            calls_user(inner, env)
+        }
+        Expr::Object(pairs) | Expr::Construct(_, pairs) => pairs.iter().any(|(_, v)| calls_user(v, env)),
+        Expr::Slice(..) | Expr::Range { .. } => operands(expr).into_iter().any(|e| calls_user(e, env)),
+    }
+}
+
+// Whether a statement (or anything nested in it) calls a user function or
+// yields. Parallel loops always run natively, one copy per thread.
+fn needs_fiber(stmt: &Stmt, env: &Env) -> bool {
+    let block = |body: &[Stmt]| body.iter().any(|s| needs_fiber(s, env));
+    match stmt {
+        Stmt::ConstDecl(_, _, e) | Stmt::VarDecl(_, _, e) | Stmt::ForceAssign(_, _, e)
+        | Stmt::Assign(_, e) | Stmt::PropAssign(_, e) | Stmt::Return(e) => calls_user(e, env),
+        Stmt::Expr(call @ Expr::Call(..)) => calls_user(call, env),
+        Stmt::Yield(_) => true,
+        Stmt::MultiIf(branches) => branches.iter().any(|(cond, body)| {
+            cond.as_ref().is_some_and(|c| calls_user(c, env)) || block(body)
+        }),
+        Stmt::Loop(LoopKind::ParallelForEach(..), _) => false,
+        Stmt::Loop(kind, body) => {
+            block(body) || match kind {
+                LoopKind::Times(e, _) | LoopKind::ForEach(_, _, e) | LoopKind::While(e) | LoopKind::Until(e)
+                | LoopKind::DoWhile(e) | LoopKind::DoUntil(e) => calls_user(e, env),
+                LoopKind::For(init, cond, step) => needs_fiber(init, env) || calls_user(cond, env) || needs_fiber(step, env),
+                LoopKind::Infinite | LoopKind::ParallelForEach(..) => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+// Operands of an expression the fiber takes apart, in evaluation order.
+fn operands(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Not(e) | Expr::Access(e, _, _) | Expr::SafeAccess(e, _, _) | Expr::Is(e, _, _, _) => vec![e],
+        Expr::Binary(l, _, r, _) | Expr::Index(l, r, _) => vec![l, r],
+        Expr::Slice(target, start, end, _) => {
+            std::iter::once(target).chain(start).chain(end).map(Box::as_ref).collect()
+        }
+        Expr::Range { start, end, step, .. } => {
+            std::iter::once(start).chain(Some(end)).chain(step).map(Box::as_ref).collect()
+        }
+        Expr::Array(items) | Expr::Variant(_, _, items) => items.iter().collect(),
+        Expr::Object(pairs) | Expr::Construct(_, pairs) => pairs.iter().map(|(_, v)| v.as_ref()).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// The same node with its operands replaced by already computed values.
+fn with_operands(expr: &Expr, vals: Vec<String>) -> Expr {
+    let mut vals = vals.into_iter().map(|v| Box::new(Expr::Str(v)));
+    let mut next = || vals.next().expect("operand count");
+    match expr {
+        Expr::Not(_) => Expr::Not(next()),
+        Expr::Access(_, prop, site) => Expr::Access(next(), prop.clone(), *site),
+        Expr::SafeAccess(_, prop, site) => Expr::SafeAccess(next(), prop.clone(), *site),
+        Expr::Is(_, ename, variant, bindings) => Expr::Is(next(), ename.clone(), variant.clone(), bindings.clone()),
+        Expr::Binary(_, op, _, site) => Expr::Binary(next(), op.clone(), next(), *site),
+        Expr::Index(_, _, site) => Expr::Index(next(), next(), *site),
+        Expr::Slice(_, start, end, site) => {
+            let target = next();
+            let start = start.as_ref().map(|_| next());
+            Expr::Slice(target, start, end.as_ref().map(|_| next()), *site)
+        }
+        Expr::Range { inclusive, step, site, .. } => {
+            let start = next();
+            let end = next();
+            Expr::Range { start, end, inclusive: *inclusive, step: step.as_ref().map(|_| next()), site: *site }
+        }
+        Expr::Array(items) => Expr::Array(items.iter().map(|_| *next()).collect()),
+        Expr::Variant(ename, variant, items) => Expr::Variant(ename.clone(), variant.clone(), items.iter().map(|_| *next()).collect()),
+        Expr::Object(pairs) => Expr::Object(pairs.iter().map(|(k, _)| (k.clone(), next())).collect()),
+        Expr::Construct(tname, pairs) => Expr::Construct(tname.clone(), pairs.iter().map(|(k, _)| (k.clone(), next())).collect()),
+        _ => unreachable!("node without operands"),
+    }
 }
 
-// Parse an array string like [1,2,3] into Vec<String>
-fn parse_array_string(s: &str) -> Vec<String> {
-    let s = s.trim();
-    if !s.starts_with('[') || !s.ends_with(']') {
-        panic!("Not an array string: '{}'", s);
+// Run a function body on a fresh fiber. Only calls reached from inside
+// operators, callbacks and bindings still nest on the native stack; those
+// stop with R100 before it runs out.
+fn exec_function<'a>(f: &'a Function, local: HashMap<String, (String, Option<String>, bool)>, env: &Env<'a>) -> String {
+    match drive(&mut Fiber::new(f, local, env.next_id()), env) {
+        Step::Done(v) => v,
+        Step::Yield(_) | Step::Suspend(_) => unreachable!("only generators yield and only tasks suspend"),
     }
-    let trimmed = &s[1..s.len()-1];
-    let mut elements = vec![];
-    let mut current = String::new();
-    let mut depth = 0;
-    for c in trimmed.chars() {
-        match c {
-            '{' | '[' => {
-                depth += 1;
-                current.push(c);
-            }
-            '}' | ']' => {
-                depth -= 1;
-                current.push(c);
-            }
-            ',' if depth == 0 => {
-                elements.push(current.trim().to_string());
-                current.clear();
+}
+
+// Run a fiber until it returns or yields. Its frames count toward the call
+// depth only while it runs; on an error they are dropped with their bindings
+// and handles.
+fn drive<'a>(fiber: &mut Fiber<'a>, env: &Env<'a>) -> Step {
+    check_native_stack(&fiber.top().func.name);
+    let base = DEPTH.get();
+    let depth = base + fiber.frames.len();
+    let max_depth = env.options.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+    if depth > max_depth {
+        fail!("R038", max_depth, fiber.top().func.name);
+    }
+    DEPTH.set(depth);
+    fiber.outer = OWNER.get();
+    OWNER.set(fiber.owner());
+    DRIVES.set(DRIVES.get() + 1);
+    let step = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| fiber.run(env)));
+    DRIVES.set(DRIVES.get() - 1);
+    DEPTH.set(base);
+    OWNER.set(fiber.outer);
+    match step {
+        Ok(step) => {
+            if let Step::Yield(v) = &step {
+                // 产出值里的 <iter#id> 交给消费者
+                env.adopt_lazies(fiber.owner(), v, fiber.outer);
             }
-            _ => current.push(c),
+            step
+        }
+        Err(payload) => {
+            fiber.abandon(env);
+            std::panic::resume_unwind(payload)
         }
     }
-    if !current.trim().is_empty() {
-        elements.push(current.trim().to_string());
+}
+
+// Parameters of `f` bound positionally, as for a plain call. Missing ones
+// are null; a typed parameter checks its argument and keeps the type.
+fn bind_args(f: &Function, args: Vec<String>, env: &Env) -> HashMap<String, (String, Option<String>, bool)> {
+    let mut args = args.into_iter();
+    f.params.iter()
+        .map(|(pname, ptype)| {
+            let val = args.next().unwrap_or_else(|| NULL.into());
+            let val = match ptype {
+                Some(t) if !env.is_known_type(t) => fail!("R039", t),
+                Some(t) if val != NULL => conform_to_type(&val, t, env).unwrap_or_else(|| fail!("R040", t, val)),
+                _ => val,
+            };
+            (pname.clone(), (val, ptype.clone(), false))
+        })
+        .collect()
+}
+
+// f(...) written out: every argument is evaluated, and more than f takes is
+// a fallback (R104).
+fn bind_call(f: &Function, args: Vec<String>, site: usize, env: &Env) -> HashMap<String, (String, Option<String>, bool)> {
+    if args.len() > f.params.len() {
+        fallback(site, "R104", &[f.name.clone(), f.params.len().to_string(), args.len().to_string()], "", env);
     }
-    elements
+    bind_args(f, args, env)
 }
 
-fn serialize_array_vec(vec: &[String]) -> String {
-    format!("[{}]", vec.join(","))
+// Record how far this thread's native stack may grow (3/4 of `size`).
+fn reserve_native_stack(size: usize) {
+    let here = 0u8;
+    let base = std::ptr::addr_of!(here) as usize;
+    STACK_LIMIT.set(base.saturating_sub(size - size / 4));
 }
 
-pub enum ExecResult {
-    None,
-    Return(String),
+fn check_native_stack(fname: &str) {
+    let here = 0u8;
+    let limit = STACK_LIMIT.get();
+    if limit != 0 && (std::ptr::addr_of!(here) as usize) < limit {
+        fail!("R100", fname);
+    }
 }
 
-pub fn execute_file(path: &str) {
+pub fn execute_file(path: &str, options: &RunOptions) {
     match fs::read_to_string(path) {
         Ok(content) => {
-            let tokens = tokenize(&content);
-            let ast = parse(&tokens);
-
-            let mut function_table: HashMap<String, &Function> = HashMap::new();
-            for f in &ast {
-                function_table.insert(f.name.clone(), f);
+            let tokens = tokenize(&content, options.keywords.as_deref());
+            for warning in confusable_warnings(&tokens) {
+                eprintln!("{}", warning);
             }
+            let mut options = options.clone();
+            if pragmas(&tokens).iter().any(|p| p == "strict") {
+                options.strict = true;
+            }
+            let program = parse(&tokens);
+            let env = Env::new(&program, &options);
 
-            if let Some(main_fn) = function_table.get("main") {
-                let mut ctx = HashMap::new();
-                exec_with_ctx(main_fn, &mut ctx, &function_table);
+            // main 本身也是一个协程；事件循环直到所有任务与定时器结束
+            if env.fns.contains_key("main") {
+                env.scheduler.lock().unwrap().spawn("main".into(), vec![]);
+                if let Err(payload) = run_threads(&env, Box::new(run_event_loop)) {
+                    std::panic::resume_unwind(payload);
+                }
             }
         },
         Err(err) => {
-            eprintln!("Failed to read file: {err}");
+            eprintln!("{}", messages::error("E001", None, &[err.to_string()]));
         }
     }
 }
 
-pub fn dump_tokens(path: &str) {
+pub fn dump_tokens(path: &str, options: &RunOptions) {
     match std::fs::read_to_string(path) {
         Ok(content) => {
-            let tokens = crate::lexer::tokenize(&content);
+            let tokens = tokenize(&content, options.keywords.as_deref());
             for token in tokens {
                 println!("{:?}", token);
             }
         },
-        Err(err) => eprintln!("Failed to read file: {err}"),
+        Err(err) => eprintln!("{}", messages::error("E001", None, &[err.to_string()])),
     }
 }
 
-pub fn dump_ast(path: &str) {
+pub fn dump_ast(path: &str, options: &RunOptions) {
     match std::fs::read_to_string(path) {
         Ok(content) => {
-            let tokens = crate::lexer::tokenize(&content);
+            let tokens = tokenize(&content, options.keywords.as_deref());
             let ast = crate::parser::parse(&tokens);
             println!("{:#?}", ast);
         },
-        Err(err) => eprintln!("Failed to read file: {err}"),
+        Err(err) => eprintln!("{}", messages::error("E001", None, &[err.to_string()])),
     }
 }
 
-pub fn syntax_check(path: &str) {
+pub fn syntax_check(path: &str, options: &RunOptions) {
     match std::fs::read_to_string(path) {
         Ok(content) => {
-            let tokens = crate::lexer::tokenize(&content);
+            let tokens = tokenize(&content, options.keywords.as_deref());
+            for warning in confusable_warnings(&tokens) {
+                eprintln!("{}", warning);
+            }
             let _ = crate::parser::parse(&tokens);
             println!("Syntax OK.");
         },
-        Err(err) => eprintln!("Failed to read file: {err}"),
+        Err(err) => eprintln!("{}", messages::error("E001", None, &[err.to_string()])),
     }
 }
 
-pub fn exec_with_ctx(
-    func: &Function,
-    ctx: &mut HashMap<String, (String, Option<String>, bool)>, // value, type, is_const
-    fns: &HashMap<String, &Function>
+// 执行一条语句；R> 与 B> 作为结果交给所在的循环或调用边界
+fn exec_stmt(
+    stmt: &Stmt,
+    ctx: &mut HashMap<String, (String, Option<String>, bool)>,
+    env: &Env
 ) -> ExecResult {
-    for stmt in &func.body {
-        match stmt {
-            Stmt::ConstDecl(name, typ, expr) => {
-                let mut val = eval_expr(expr, ctx, fns);
-                let inferred_type = typ.clone().or_else(|| infer_type(&val));
-                let expected_type = typ.clone().unwrap_or_else(|| inferred_type.clone().unwrap_or("string".into()));
-                if let Some(t) = &typ {
-                    if !["int", "float", "bool", "string", "obj"].contains(&t.as_str()) {
-                        panic!("Unknown type '{}'", t);
-                    }
-                }
-                if expected_type == "bool" {
-                    let normalized = val.trim_matches('"').to_lowercase();
-                    val = match normalized.as_str() {
-                        "true" | "1" => "true".to_string(),
-                        "false" | "0" => "false".to_string(),
-                        _ => panic!("Invalid boolean literal: '{}'", val),
-                    };
-                } else if expected_type == "int" {
-                    val.parse::<i64>().unwrap_or_else(|_| panic!("Invalid int literal: '{}'", val));
-                } else if expected_type == "float" {
-                    val.parse::<f64>().unwrap_or_else(|_| panic!("Invalid float literal: '{}'", val));
-                } else if expected_type == "string" {
-                    if val.starts_with('"') && val.ends_with('"') {
-                        val = val[1..val.len()-1].to_string(); // 去除引号
-                    } else {
-                        val = val.to_string(); // 放宽要求，允许非引号包裹的字符串（如对象、数组或字面量）
-                    }
+    match stmt {
+        Stmt::ConstDecl(name, typ, expr) => {
+            check_declarable(name);
+            let mut val = eval_expr(expr, ctx, env);
+            let inferred_type = typ.clone().or_else(|| infer_type(&val, env));
+            let expected_type = typ.clone().unwrap_or_else(|| inferred_type.clone().unwrap_or("string".into()));
+            if let Some(t) = &typ
+                && !env.is_known_type(t) {
+                fail!("R039", t);
+            }
+            if val == NULL {
+                // null 可以放进任何类型的变量
+            } else if env.is_user_type(&expected_type) {
+                if conform_to_type(&val, &expected_type, env).is_none() {
+                    fail!("R040", expected_type, val);
+                }
+            } else if expected_type == "bool" {
+                let normalized = val.trim_matches('"').to_lowercase();
+                val = match normalized.as_str() {
+                    "true" | "1" => "true".to_string(),
+                    "false" | "0" => "false".to_string(),
+                    _ => fail!("R041", val),
+                };
+            } else if expected_type == "int" {
+                val.parse::<i64>().unwrap_or_else(|_| fail!("R042", val));
+            } else if expected_type == "float" {
+                val = float_text(val.parse::<f64>().unwrap_or_else(|_| fail!("R043", val)));
+            } else if expected_type == "string" {
+                if val.starts_with('"') && val.ends_with('"') {
+                    val = val[1..val.len()-1].to_string(); // 去除引号
+                } else {
+                    val = val.to_string(); // 放宽要求，允许非引号包裹的字符串（如对象、数组或字面量）
                 }
+            }
+
+            if let Some((_, _, true)) = ctx.get(name) {
+                fail!("R028", name);
+            }
+
+            ctx.insert(name.clone(), (val, Some(expected_type), typ.is_some()));
+            env.invalidate(name, ctx);
+        }
 
-                if let Some((_, _, true)) = ctx.get(name) {
-                    panic!("Cannot redefine constant '{}'", name);
+        Stmt::VarDecl(name, typ, expr) => {
+            check_declarable(name);
+            let mut val = eval_expr(expr, ctx, env);
+            let expected_type = typ.clone().unwrap_or_else(|| infer_type(&val, env).unwrap_or("string".into()));
+            if let Some(t) = &typ
+                && !env.is_known_type(t) {
+                fail!("R039", t);
+            }
+            if val == NULL {
+                // null 可以放进任何类型的变量
+            } else if env.is_user_type(&expected_type) {
+                if conform_to_type(&val, &expected_type, env).is_none() {
+                    fail!("R040", expected_type, val);
+                }
+            } else if expected_type == "bool" {
+                let normalized = val.trim_matches('"').to_lowercase();
+                val = match normalized.as_str() {
+                    "true" | "1" => "true".to_string(),
+                    "false" | "0" => "false".to_string(),
+                    _ => fail!("R041", val),
+                };
+            } else if expected_type == "int" {
+                val.parse::<i64>().unwrap_or_else(|_| fail!("R042", val));
+            } else if expected_type == "float" {
+                val = float_text(val.parse::<f64>().unwrap_or_else(|_| fail!("R043", val)));
+            } else if expected_type == "string" {
+                if val.starts_with('"') && val.ends_with('"') {
+                    val = val[1..val.len()-1].to_string(); // 去除引号
+                } else {
+                    val = val.to_string(); // 放宽要求，允许非引号包裹的字符串（如对象、数组或字面量）
                 }
+            }
+            // 未标注类型的 null 变量不锁定类型
+            let stored_type = if val == NULL && typ.is_none() { None } else { Some(expected_type) };
+            ctx.insert(name.clone(), (val, stored_type, false));
+            env.invalidate(name, ctx);
+        }
 
-                ctx.insert(name.clone(), (val, Some(expected_type), typ.is_some()));
+        Stmt::ForceAssign(name, typ, expr) => {
+            let value = eval_expr(expr, ctx, env);
+            let (old_type, is_const) = match ctx.get(name) {
+                Some((_, t, c)) => (t.clone(), *c),
+                None => fail!("R044", name),
+            };
+            if is_const && env.options.strict {
+                eprintln!("{}", messages::warning("W001", &[name.to_string()]));
             }
+            // 未写类型时转换为原类型，转换失败报 R088；要改类型须写 x(type) =:: value
+            let target = match typ {
+                Some(t) => t.clone(),
+                None => match old_type.as_deref() {
+                    Some(t) if t != "bind" => t.to_string(),
+                    _ => infer_type(&value, env).unwrap_or_else(|| "string".into()),
+                },
+            };
+            let converted = convert_value(&value, &target, env).unwrap_or_else(|e| panic!("{}", e));
+            ctx.insert(name.clone(), (converted, Some(target), is_const));
+            env.invalidate(name, ctx);
+        }
 
-            Stmt::VarDecl(name, typ, expr) => {
-                let mut val = eval_expr(expr, ctx, fns);
-                let expected_type = typ.clone().unwrap_or_else(|| infer_type(&val).unwrap_or("string".into()));
-                if expected_type == "bool" {
-                    let normalized = val.trim_matches('"').to_lowercase();
-                    val = match normalized.as_str() {
-                        "true" | "1" => "1".to_string(),
-                        "false" | "0" => "0".to_string(),
-                        _ => panic!("Invalid boolean literal: '{}'", val),
-                    };
-                } else if expected_type == "int" {
-                    val.parse::<i64>().unwrap_or_else(|_| panic!("Invalid int literal: '{}'", val));
-                } else if expected_type == "float" {
-                    val.parse::<f64>().unwrap_or_else(|_| panic!("Invalid float literal: '{}'", val));
-                } else if expected_type == "string" {
-                    if val.starts_with('"') && val.ends_with('"') {
-                        val = val[1..val.len()-1].to_string(); // 去除引号
-                    } else {
-                        val = val.to_string(); // 放宽要求，允许非引号包裹的字符串（如对象、数组或字面量）
+        Stmt::Bind(name, expr) => {
+            check_declarable(name);
+            declare_binding(name, expr.clone(), false, ctx, env);
+        }
+
+        Stmt::BindTwo(name, other) => {
+            check_declarable(name);
+            if !ctx.contains_key(other) {
+                fail!("R044", other);
+            }
+            declare_binding(name, Expr::Ident(other.clone()), true, ctx, env);
+        }
+
+        Stmt::Expr(Expr::Call(fname, args, site)) => {
+            if fname == "print" {
+                let output: Vec<String> = args.iter().map(|e| {
+                    match e {
+                        Expr::Str(s) => s.clone(),
+                        _ => eval_expr(e, ctx, env)
                     }
+                }).collect();
+                emit(&output.join(" "));
+            } else if let Some(f) = env.fns.get(fname) {
+                let mut local_ctx = HashMap::new();
+                for (i, (pname, _ptype)) in f.params.iter().enumerate() {
+                    let arg_val = args.get(i).map(|e| eval_expr(e, ctx, env)).unwrap_or_else(|| NULL.into());
+                    local_ctx.insert(pname.clone(), (arg_val, None, false));
                 }
-                ctx.insert(name.clone(), (val, Some(expected_type), false));
+                // 作为语句调用时丢弃返回值。循环体现在会把 R> 向外传递，
+                // 若这里仍把被调函数的返回值当成本函数的 R>，循环里的 helper() 就会结束整个调用者
+                run_function(f, local_ctx, env);
+            } else if BUILTIN_METHODS.contains(&fname.as_str()) {
+                let passed = args.iter().map(|e| eval_expr(e, ctx, env)).collect();
+                call_builtin(fname, passed, env);
+            } else {
+                fallback(*site, "R015", &[fname.to_string()], NULL, env);
             }
+        }
 
-            Stmt::Expr(Expr::Call(fname, args)) => {
-                if fname == "print" {
-                    let output: Vec<String> = args.iter().map(|e| {
-                        match e {
-                            Expr::Str(s) => s.clone(),
-                            _ => eval_expr(e, ctx, fns)
-                        }
-                    }).collect();
-                    println!("{}", output.join(" "));
-                } else if let Some(f) = fns.get(fname) {
-                    let mut local_ctx = HashMap::new();
-                    for (i, (pname, _ptype)) in f.params.iter().enumerate() {
-                        let arg_val = args.get(i).map(|e| eval_expr(e, ctx, fns)).unwrap_or_default();
-                        local_ctx.insert(pname.clone(), (arg_val, None, false));
-                    }
-                    let result = exec_with_ctx(f, &mut local_ctx, fns);
-                    if let ExecResult::Return(v) = result {
-                        return ExecResult::Return(v);
-                    }
-                }
-            }
-
-            Stmt::Return(expr) => {
-                let val = eval_expr(expr, ctx, fns);
-                return ExecResult::Return(val);
-            }
-
-            Stmt::Assign(name, expr) => {
-                let value = eval_expr(expr, ctx, fns);
-                if let Some((_, typ, is_const)) = ctx.get(name) {
-                    if *is_const {
-                        panic!("Cannot assign to constant '{}'", name);
-                    }
-                    let enforced = if let Some(t) = typ {
-                        if t == "int" {
-                            value.parse::<i64>().unwrap_or_else(|_| panic!("Type mismatch: expected int, got '{}'", value)).to_string()
-                        } else if t == "float" {
-                            value.parse::<f64>().unwrap_or_else(|_| panic!("Type mismatch: expected float, got '{}'", value)).to_string()
-                        } else if t == "bool" {
-                            let v = value.to_lowercase();
-                            match v.as_str() {
-                                "true" | "1"  => "true".to_string(),
-                                "false" | "0" => "false".to_string(),
-                                _ => panic!("Type mismatch: expected bool, got '{}'", value)
-                            }
-                        } else if t == "string" {
-                            value
-                        } else {
-                            panic!("Unsupported type '{}'", t);
+        Stmt::Return(expr) => {
+            let val = eval_expr(expr, ctx, env);
+            return ExecResult::Return(val);
+        }
+
+        Stmt::Assign(name, expr) => {
+            let value = eval_expr(expr, ctx, env);
+            if let Some(target) = binding_write_target(name, ctx, env) {
+                // 双向绑定：写入转发给被绑定的变量
+                let forwarded = exec_stmt(&Stmt::Assign(target, Expr::Str(value)), ctx, env);
+                if let ExecResult::Return(v) = forwarded {
+                    return ExecResult::Return(v);
+                }
+            } else if let Some((_, typ, is_const)) = ctx.get(name) {
+                if *is_const {
+                    fail!("R045", name);
+                }
+                let enforced = if value == NULL {
+                    value
+                } else if let Some(t) = typ {
+                    if t == "int" {
+                        value.parse::<i64>().unwrap_or_else(|_| fail!("R040", "int", value)).to_string()
+                    } else if t == "float" {
+                        float_text(value.parse::<f64>().unwrap_or_else(|_| fail!("R040", "float", value)))
+                    } else if t == "bool" {
+                        let v = value.to_lowercase();
+                        match v.as_str() {
+                            "true" | "1"  => "true".to_string(),
+                            "false" | "0" => "false".to_string(),
+                            _ => fail!("R040", "bool", value)
                         }
-                    } else {
+                    } else if t == "string" {
                         value
-                    };
-                    ctx.insert(name.clone(), (enforced, typ.clone(), false));
+                    } else if env.is_known_type(t) {
+                        conform_to_type(&value, t, env)
+                            .unwrap_or_else(|| fail!("R040", t, value))
+                    } else {
+                        fail!("R046", t);
+                    }
                 } else {
-                    panic!("Undefined variable '{}'", name);
-                }
+                    value
+                };
+                ctx.insert(name.clone(), (enforced, typ.clone(), false));
+                env.invalidate(name, ctx);
+            } else {
+                fail!("R044", name);
             }
+        }
 
-            Stmt::Loop(kind, body) => {
-                match kind.clone() {
-                    crate::ast::LoopKind::Times(expr) => {
-                        let count = eval_expr(&expr, ctx, fns)
-                            .parse::<usize>()
-                            .unwrap_or_else(|_| panic!("Invalid loop count: {:?}", expr));
-                        for i in 0..count {
-                            ctx.insert("_".to_string(), (i.to_string(), Some("int".to_string()), false));
-                            for stmt in body {
-                                exec_with_ctx(&Function {
-                                    name: "<loop>".into(),
-                                    params: vec![],
-                                    body: vec![stmt.clone()],
-                                }, ctx, fns);
-                            }
-                        }
+        Stmt::Loop(kind, body) => {
+            // B> 结束循环；R> 结束所在函数
+            macro_rules! run_body {
+                () => {
+                    match run_loop_body(body, ctx, env) {
+                        ExecResult::Break => break,
+                        ExecResult::None => {}
+                        result => return result,
                     }
-                    crate::ast::LoopKind::While(expr) => {
-                        while {
-                            let cond = eval_expr(&expr, ctx, fns);
-                            cond != "0" && cond.to_lowercase() != "false" && cond != "<undef>"
-                        } {
-                            for stmt in body {
-                                exec_with_ctx(&Function {
-                                    name: "<while>".into(),
-                                    params: vec![],
-                                    body: vec![stmt.clone()],
-                                }, ctx, fns);
-                            }
-                        }
+                };
+            }
+            match kind.clone() {
+                LoopKind::Times(expr, counter) => {
+                    let count = eval_expr(&expr, ctx, env)
+                        .parse::<usize>()
+                        .unwrap_or_else(|_| fail!("R047", format!("{:?}", expr)));
+                    let counter = counter.unwrap_or_else(|| "_".to_string());
+                    for i in 0..count {
+                        ctx.insert(counter.clone(), (i.to_string(), Some("int".to_string()), false));
+                        env.invalidate(&counter, ctx);
+                        run_body!();
                     }
-                    crate::ast::LoopKind::ForEach(var, expr) => {
-                        let list_val = eval_expr(&expr, ctx, fns);
-                        if list_val.starts_with('[') && list_val.ends_with(']') {
-                            let trimmed = &list_val[1..list_val.len()-1];
-                            let mut elements = vec![];
-                            let mut current = String::new();
-                            let mut depth = 0;
-
-                            for c in trimmed.chars() {
-                                match c {
-                                    '[' | '{' => {
-                                        depth += 1;
-                                        current.push(c);
-                                    }
-                                    ']' | '}' => {
-                                        depth -= 1;
-                                        current.push(c);
-                                    }
-                                    ',' if depth == 0 => {
-                                        elements.push(current.trim().to_string());
-                                        current.clear();
-                                    }
-                                    _ => current.push(c),
-                                }
-                            }
-                            if !current.trim().is_empty() {
-                                elements.push(current.trim().to_string());
-                            }
-
-                            for el in elements {
-                                ctx.insert(var.clone(), (el.to_string(), Some("string".to_string()), false));
-                                for stmt in body {
-                                    exec_with_ctx(&Function {
-                                        name: "<foreach>".into(),
-                                        params: vec![],
-                                        body: vec![stmt.clone()],
-                                    }, ctx, fns);
-                                }
-                            }
-                        } else {
-                            panic!("For-each target is not an array: {}", list_val);
+                }
+                LoopKind::While(expr) => {
+                    while truthy(&eval_expr(&expr, ctx, env)) {
+                        run_body!();
+                    }
+                }
+                LoopKind::Until(expr) => {
+                    while !truthy(&eval_expr(&expr, ctx, env)) {
+                        run_body!();
+                    }
+                }
+                LoopKind::Infinite => loop {
+                    run_body!();
+                },
+                LoopKind::DoWhile(expr) => loop {
+                    run_body!();
+                    if !truthy(&eval_expr(&expr, ctx, env)) {
+                        break;
+                    }
+                },
+                LoopKind::DoUntil(expr) => loop {
+                    run_body!();
+                    if truthy(&eval_expr(&expr, ctx, env)) {
+                        break;
+                    }
+                },
+                LoopKind::ForEach(index_var, var, expr) => {
+                    let list_val = eval_expr(&expr, ctx, env);
+                    let mut entries = foreach_entries(&list_val, env);
+                    let key_type = if list_val.starts_with('{') { "string" } else { "int" };
+                    while let Some((key, el)) = entries.next(env) {
+                        if let Some(index_var) = &index_var {
+                            ctx.insert(index_var.clone(), (key, Some(key_type.to_string()), false));
+                            env.invalidate(index_var, ctx);
                         }
+                        let item_type = infer_type(&el, env);
+                        ctx.insert(var.clone(), (el, item_type, false));
+                        env.invalidate(&var, ctx);
+                        run_body!();
                     }
-                    crate::ast::LoopKind::For(init, cond, step) => {
-                        exec_with_ctx(&Function {
-                            name: "<for-init>".into(),
-                            params: vec![],
-                            body: vec![*init.clone()],
-                        }, ctx, fns);
-
-                        while {
-                            let cond_val = eval_expr(&cond, ctx, fns);
-                            cond_val != "0" && cond_val.to_lowercase() != "false" && cond_val != "<undef>"
-                        } {
-                            for stmt in body {
-                                exec_with_ctx(&Function {
-                                    name: "<for-body>".into(),
-                                    params: vec![],
-                                    body: vec![stmt.clone()],
-                                }, ctx, fns);
+                }
+                LoopKind::ParallelForEach(index_var, var, expr) => {
+                    let list_val = eval_expr(&expr, ctx, env);
+                    let key_type = if list_val.starts_with('{') { "string" } else { "int" };
+                    let mut entries = foreach_entries(&list_val, env);
+                    let entries: Vec<(String, String)> = std::iter::from_fn(|| entries.next(env)).collect();
+                    let base: &HashMap<String, (String, Option<String>, bool)> = ctx;
+                    // 每次迭代使用外层变量的副本，输出按元素顺序打印
+                    let outputs = run_parallel(entries.len(), |i| {
+                        let (key, el) = entries[i].clone();
+                        let mut local = base.clone();
+                        if let Some(index_var) = &index_var {
+                            local.insert(index_var.clone(), (key, Some(key_type.to_string()), false));
+                        }
+                        let item_type = infer_type(&el, env);
+                        local.insert(var.clone(), (el, item_type, false));
+                        capture_output(|| {
+                            if !matches!(run_loop_body(body, &mut local, env), ExecResult::None) {
+                                fail!("R048");
                             }
+                        })
+                    });
+                    for out in outputs {
+                        emit_raw(&out);
+                    }
+                }
+                LoopKind::For(init, cond, step) => {
+                    exec_stmt(&init, ctx, env);
 
-                            // Execute the step statement
-                            exec_with_ctx(&Function {
-                                name: "<for-step>".into(),
-                                params: vec![],
-                                body: vec![*step.clone()],
-                            }, ctx, fns);
-                        }
+                    while truthy(&eval_expr(&cond, ctx, env)) {
+                        run_body!();
+
+                        // Execute the step statement
+                        exec_stmt(&step, ctx, env);
                     }
                 }
             }
+        }
 
-            // Stmt::IfElse removed
+        Stmt::Break => return ExecResult::Break,
 
-            Stmt::MultiIf(branches) => {
-                let mut executed = false;
-                for (cond, body) in branches {
-                    if executed {
-                        break;
-                    }
-                    let passed = match cond {
-                        Some(expr) => {
-                            let v = eval_expr(expr, ctx, fns);
-                            v != "0" && v.to_lowercase() != "false" && v != "<undef>"
-                        }
-                        None => true
-                    };
-                    if passed {
-                        for stmt in body {
-                            exec_with_ctx(&Function {
-                                name: "<if>".into(),
-                                params: vec![],
-                                body: vec![stmt.clone()],
-                            }, ctx, fns);
-                        }
-                        executed = true;
-                    }
+        // 生成器体由 Fiber 执行；直接执行到这里说明 Y> 在并行循环里
+        Stmt::Yield(_) => fail!("R101"),
+
+        // Stmt::IfElse removed
+
+        Stmt::MultiIf(branches) => {
+            for (cond, body) in branches {
+                let passed = match cond {
+                    Some(expr) => truthy(&eval_expr(expr, ctx, env)),
+                    None => true
+                };
+                // R> / B> 在分支内部也要结束所在函数或循环
+                if passed {
+                    return run_loop_body(body, ctx, env);
                 }
             }
-            Stmt::Increment(var) => {
-                // println!("DEBUG: Incrementing '{}' from {:?}", var, ctx.get(var).unwrap());
-                let (current_str, typ, is_const) = ctx.get(var)
-                    .expect(&format!("Variable '{}' not found for increment", var))
-                    .clone();
-                let new_str = match typ.as_deref() {
-                    Some("int") => {
-                        let n = current_str.parse::<i64>()
-                            .unwrap_or_else(|_| panic!("Invalid int for increment: '{}'", current_str));
-                        (n + 1).to_string()
-                    }
-                    Some("float") => {
-                        let f = current_str.parse::<f64>()
-                            .unwrap_or_else(|_| panic!("Invalid float for increment: '{}'", current_str));
-                        (f + 1.0).to_string()
+        }
+        Stmt::Increment(var) => {
+            // println!("DEBUG: Incrementing '{}' from {:?}", var, ctx.get(var).unwrap());
+            if let Some(target) = binding_write_target(var, ctx, env) {
+                exec_stmt(&Stmt::Increment(target), ctx, env);
+                return ExecResult::None;
+            }
+            let (current_str, typ, is_const) = ctx.get(var)
+                .unwrap_or_else(|| fail!("R049", var))
+                .clone();
+            let new_str = match typ.as_deref() {
+                Some("int") => {
+                    let n = current_str.parse::<i64>()
+                        .unwrap_or_else(|_| fail!("R050", current_str));
+                    (n + 1).to_string()
+                }
+                Some("float") => {
+                    let f = current_str.parse::<f64>()
+                        .unwrap_or_else(|_| fail!("R051", current_str));
+                    float_text(f + 1.0)
+                }
+                _ => fail!("R052", format!("{:?}", typ)),
+            };
+            ctx.insert(var.clone(), (new_str, typ.clone(), is_const));
+            env.invalidate(var, ctx);
+        }
+        Stmt::Decrement(var) => {
+            if let Some(target) = binding_write_target(var, ctx, env) {
+                exec_stmt(&Stmt::Decrement(target), ctx, env);
+                return ExecResult::None;
+            }
+            let (current_str, typ, is_const) = ctx.get(var)
+                .unwrap_or_else(|| fail!("R053", var))
+                .clone();
+            let new_str = match typ.as_deref() {
+                Some("int") => {
+                    let n = current_str.parse::<i64>()
+                        .unwrap_or_else(|_| fail!("R054", current_str));
+                    (n - 1).to_string()
+                }
+                Some("float") => {
+                    let f = current_str.parse::<f64>()
+                        .unwrap_or_else(|_| fail!("R055", current_str));
+                    float_text(f - 1.0)
+                }
+                _ => fail!("R056", format!("{:?}", typ)),
+            };
+            ctx.insert(var.clone(), (new_str, typ.clone(), is_const));
+            env.invalidate(var, ctx);
+        }
+        Stmt::PropAssign(lhs, rhs) => {
+            // Evaluate right-hand side
+            let val_str = eval_expr(rhs, ctx, env);
+
+            // Determine left-hand side
+            match lhs.as_ref() {
+                // Support nested property assignment: obj.field, obj.field1.field2, etc.
+                Expr::Access(..) => {
+                    // Build path from nested Access
+                    let mut path = Vec::new();
+                    let mut expr = lhs.as_ref();
+                    while let Expr::Access(inner, field, _) = expr {
+                        path.push(field.clone());
+                        expr = inner.as_ref();
                     }
-                    _ => panic!("Unsupported type '{:?}' for increment", typ),
-                };
-                ctx.insert(var.clone(), (new_str, typ.clone(), is_const));
-            }
-            Stmt::Decrement(var) => {
-                let (current_str, typ, is_const) = ctx.get(var)
-                    .expect(&format!("Variable '{}' not found for decrement", var))
-                    .clone();
-                let new_str = match typ.as_deref() {
-                    Some("int") => {
-                        let n = current_str.parse::<i64>()
-                            .unwrap_or_else(|_| panic!("Invalid int for decrement: '{}'", current_str));
-                        (n - 1).to_string()
-                    }
-                    Some("float") => {
-                        let f = current_str.parse::<f64>()
-                            .unwrap_or_else(|_| panic!("Invalid float for decrement: '{}'", current_str));
-                        (f - 1.0).to_string()
-                    }
-                    _ => panic!("Unsupported type '{:?}' for decrement", typ),
-                };
-                ctx.insert(var.clone(), (new_str, typ.clone(), is_const));
-            }
-            Stmt::PropAssign(lhs, rhs) => {
-                // Evaluate right-hand side
-                let val_str = eval_expr(rhs, ctx, fns);
-
-                // Determine left-hand side
-                match lhs.as_ref() {
-                    // Support nested property assignment: obj.field, obj.field1.field2, etc.
-                    Expr::Access(_, _) => {
-                        // Build path from nested Access
-                        let mut path = Vec::new();
-                        let mut expr = lhs.as_ref();
-                        while let Expr::Access(inner, field) = expr {
-                            path.push(field.clone());
-                            expr = inner.as_ref();
+                    // Reverse so that path[0] is the top-level key
+                    path.reverse();
+                    // expr should now be the root identifier
+                    if let Expr::Ident(root) = expr {
+                        if binding_id(ctx.get(root).map_or("", |v| v.0.as_str())).is_some() {
+                            fail!("R030", root);
                         }
-                        // Reverse so that path[0] is the top-level key
-                        path.reverse();
-                        // expr should now be the root identifier
-                        if let Expr::Ident(root) = expr {
-                            if let Some(ctx_val) = ctx.get_mut(root) {
-                                let new_obj = update_object_str(&ctx_val.0, &path, val_str.clone());
-                                ctx_val.0 = new_obj;
-                            } else {
-                                panic!("'{}' is not found for nested assignment", root);
-                            }
+                        if let Some(ctx_val) = ctx.get_mut(root) {
+                            let new_obj = update_object_str(&ctx_val.0, &path, val_str.clone(), env);
+                            ctx_val.0 = new_obj;
+                            env.invalidate(root, ctx);
                         } else {
-                            panic!("Invalid nested left-hand side: {:?}", lhs);
+                            fail!("R057", root);
                         }
+                    } else {
+                        fail!("R058", format!("{:?}", lhs));
                     }
-                    // arr[index] and nested arrays: full support for nested Index/Access, now with _append
-                    Expr::Index(arr_expr, idx_expr) => {
-                        // Evaluate original array string
-                        let arr_str = eval_expr(arr_expr, ctx, fns);
-                        // Parse into Vec<String>
-                        let mut vec = parse_array_string(&arr_str);
+                }
+                // arr[index] and nested arrays: full support for nested Index/Access, now with _append
+                Expr::Index(arr_expr, idx_expr, _) => {
+                    // Evaluate original array string
+                    let arr_str = eval_expr(arr_expr, ctx, env);
+                    // Parse into Vec<String>
+                    let mut vec = parse_array_string(&arr_str);
 
-                        // Determine if append operation
-                        let append_mode = matches!(idx_expr.as_ref(), Expr::Ident(s) if s == "_append");
+                    // Determine if append operation
+                    let append_mode = matches!(idx_expr.as_ref(), Expr::Ident(s) if s == "_append");
 
-                        // Compute index or use push
-                        if append_mode {
-                            vec.push(val_str.clone());
-                        } else {
-                            // parse numeric index
-                            let idx = eval_expr(idx_expr, ctx, fns)
-                                .parse::<usize>()
-                                .unwrap_or_else(|_| panic!("Invalid index for '{:?}'", arr_expr));
-                            if idx >= vec.len() {
-                                panic!("Index {} out of bounds for {:?}", idx, arr_expr);
-                            }
-                            vec[idx] = val_str.clone();
+                    // Compute index or use push
+                    if append_mode {
+                        vec.push(val_str.clone());
+                    } else {
+                        // parse numeric index
+                        let idx = eval_expr(idx_expr, ctx, env)
+                            .parse::<usize>()
+                            .unwrap_or_else(|_| fail!("R059", format!("{:?}", arr_expr)));
+                        if idx >= vec.len() {
+                            fail!("R060", idx, format!("{:?}", arr_expr));
                         }
+                        vec[idx] = val_str.clone();
+                    }
 
-                        // Serialize back
-                        let new_arr_str = serialize_array_vec(&vec);
+                    // Serialize back
+                    let new_arr_str = serialize_array_vec(&vec);
 
-                        // Assign back to variable or nested object field
-                        match arr_expr.as_ref() {
-                            Expr::Ident(arr_name) => {
-                                if let Some(ctx_val) = ctx.get_mut(arr_name) {
-                                    ctx_val.0 = new_arr_str;
-                                } else {
-                                    panic!("'{}' is not defined", arr_name);
-                                }
+                    // Assign back to variable or nested object field
+                    match arr_expr.as_ref() {
+                        Expr::Ident(arr_name) => {
+                            if binding_id(ctx.get(arr_name).map_or("", |v| v.0.as_str())).is_some() {
+                                fail!("R030", arr_name);
                             }
-                            // nested array in object
-                            _ => {
-                                // Build path for nested object
-                                let mut path = Vec::new();
-                                let mut expr = arr_expr.as_ref();
-                                while let Expr::Access(inner, field) = expr {
-                                    path.push(field.clone());
-                                    expr = inner.as_ref();
+                            if let Some(ctx_val) = ctx.get_mut(arr_name) {
+                                ctx_val.0 = new_arr_str;
+                                env.invalidate(arr_name, ctx);
+                            } else {
+                                fail!("R061", arr_name);
+                            }
+                        }
+                        // nested array in object
+                        _ => {
+                            // Build path for nested object
+                            let mut path = Vec::new();
+                            let mut expr = arr_expr.as_ref();
+                            while let Expr::Access(inner, field, _) = expr {
+                                path.push(field.clone());
+                                expr = inner.as_ref();
+                            }
+                            path.reverse();
+                            if let Expr::Ident(root) = expr {
+                                if binding_id(ctx.get(root).map_or("", |v| v.0.as_str())).is_some() {
+                                    fail!("R030", root);
                                 }
-                                path.reverse();
-                                if let Expr::Ident(root) = expr {
-                                    if let Some(ctx_val) = ctx.get_mut(root) {
-                                        let updated = update_object_str(&ctx_val.0, &path, new_arr_str.clone());
-                                        ctx_val.0 = updated;
-                                    } else {
-                                        panic!("'{}' is not found for nested array assignment", root);
-                                    }
+                                if let Some(ctx_val) = ctx.get_mut(root) {
+                                    let updated = update_object_str(&ctx_val.0, &path, new_arr_str.clone(), env);
+                                    ctx_val.0 = updated;
+                                    env.invalidate(root, ctx);
                                 } else {
-                                    panic!("Invalid nested array target: {:?}", arr_expr);
+                                    fail!("R062", root);
                                 }
+                            } else {
+                                fail!("R063", format!("{:?}", arr_expr));
                             }
                         }
                     }
-                    _ => panic!("Invalid left-hand side in property assignment: {:?}", lhs),
                 }
+                _ => fail!("R064", format!("{:?}", lhs)),
             }
-            _ => {}
         }
+        _ => {}
     }
-
     ExecResult::None
 }
 
 fn eval_expr(
     expr: &Expr,
     ctx: &mut HashMap<String, (String, Option<String>, bool)>,
-    fns: &HashMap<String, &Function>
+    env: &Env
 ) -> String {
     match expr {
         Expr::Not(inner) => {
-            // Evaluate the inner expression and invert its boolean value
-            let val = eval_expr(inner, ctx, fns);
-            // Treat empty string, "0", or "false" as false; everything else as true
-            let is_truthy = !(val.is_empty() || val == "0" || val.eq_ignore_ascii_case("false"));
-            (!is_truthy).to_string()
+            bool_value(!truthy(&eval_expr(inner, ctx, env)))
         }
-        Expr::MethodCall { target, name, args } => {
+        Expr::MethodCall { target, name, args, site } => {
             // 1) 先算 target
-            let mut passed = vec![eval_expr(target, ctx, fns)];
+            let mut passed = vec![eval_expr(target, ctx, env)];
             // 2) 其余实参
             for a in args {
-                passed.push(eval_expr(a, ctx, fns));
-            }
-
-            // 内置优先
-            if let Some(ret) = call_builtin(name, passed.clone()) {
-                return ret;
+                passed.push(eval_expr(a, ctx, env));
             }
 
-            // 再找用户函数
-            if let Some(u) = fns.get(name) {
-                // 临时上下文，把 receiver 作为第一个形参（若存在）
-                let mut local = HashMap::new();
-                if let Some((p0, _)) = u.params.get(0) {
-                    local.insert(p0.clone(), (passed[0].clone(), None, false));
-                }
-                // 对于其余参数，无论是否传值，都绑定；缺省时用 "<undef>"
-                for (i, (pn, _)) in u.params.iter().enumerate().skip(1) {
-                    // Bind missing extra args to "<undef>" so that if(c) treats it as false
-                    let arg_val = passed
-                        .get(i)
-                        .cloned()
-                        .unwrap_or_else(|| "<undef>".to_string());
-                    local.insert(pn.clone(), (arg_val, None, false));
-                }
-                match exec_with_ctx(u, &mut local, fns) {
-                    ExecResult::Return(v) => v,
-                    _ => "<void>".into(),
+            // 内置 → 类型方法 → 普通函数
+            let recv_type = infer_type(&passed[0], env).unwrap_or_else(|| "string".into());
+            let target_fn = match env.resolve_method(*site, &recv_type, name) {
+                Some(Dispatch::Builtin) => {
+                    return call_builtin(name, passed, env).expect("builtin method");
                 }
+                Some(Dispatch::Method(m)) | Some(Dispatch::Free(m)) => Some(m),
+                None => None,
+            };
+
+            if let Some(u) = target_fn {
+                call_with_receiver(u, passed, env)
             } else {
-                panic!("Unknown method '{}'", name);
+                fail!("R065", name);
             }
         }
         Expr::PostfixIncrement(var) => {
             // Evaluate and apply postfix increment: return new value after increment
             let (current_str, typ, _is_const) = ctx.get(var)
-                .expect(&format!("Undefined identifier in postfix ++: '{}'", var))
+                .unwrap_or_else(|| fail!("R066", var))
                 .clone();
             let old = current_str.clone();
             let new_str = match typ.as_deref() {
                 Some("int") => {
                     let n = old.parse::<i64>()
-                        .unwrap_or_else(|_| panic!("Invalid int in postfix ++: '{}'", old));
+                        .unwrap_or_else(|_| fail!("R067", old));
                     (n + 1).to_string()
                 }
                 Some("float") => {
                     let f = old.parse::<f64>()
-                        .unwrap_or_else(|_| panic!("Invalid float in postfix ++: '{}'", old));
-                    (f + 1.0).to_string()
+                        .unwrap_or_else(|_| fail!("R068", old));
+                    float_text(f + 1.0)
                 }
-                _ => panic!("Unsupported type '{:?}' for postfix ++ on '{}'", typ, var),
+                _ => fail!("R069", format!("{:?}", typ), var),
             };
             ctx.insert(var.clone(), (new_str.clone(), typ.clone(), _is_const));
+            env.invalidate(var, ctx);
             new_str
         }
         Expr::PostfixDecrement(var) => {
             // Evaluate and apply postfix decrement: return new value after decrement
             let (current_str, typ, _is_const) = ctx.get(var)
-                .expect(&format!("Undefined identifier in postfix --: '{}'", var))
+                .unwrap_or_else(|| fail!("R070", var))
                 .clone();
             let old = current_str.clone();
             let new_str = match typ.as_deref() {
                 Some("int") => {
                     let n = old.parse::<i64>()
-                        .unwrap_or_else(|_| panic!("Invalid int in postfix --: '{}'", old));
+                        .unwrap_or_else(|_| fail!("R071", old));
                     (n - 1).to_string()
                 }
                 Some("float") => {
                     let f = old.parse::<f64>()
-                        .unwrap_or_else(|_| panic!("Invalid float in postfix --: '{}'", old));
-                    (f - 1.0).to_string()
+                        .unwrap_or_else(|_| fail!("R072", old));
+                    float_text(f - 1.0)
                 }
-                _ => panic!("Unsupported type '{:?}' for postfix -- on '{}'", typ, var),
+                _ => fail!("R073", format!("{:?}", typ), var),
             };
             ctx.insert(var.clone(), (new_str.clone(), typ.clone(), _is_const));
+            env.invalidate(var, ctx);
             new_str
         }
         Expr::Number(n) => n.to_string(),
         Expr::Str(s) => s.clone(),
         Expr::Ident(id) => {
             match id.as_str() {
-                "true" => "1".to_string(),
-                "false" => "0".to_string(),
+                "true" | "false" => id.clone(),
+                "null" => NULL.to_string(),
                 _ => {
                     if let Some((val, _, _)) = ctx.get(id) {
-                        val.clone()
-                    } else if id.parse::<i64>().is_ok() || id.parse::<f64>().is_ok() || ["<null>", "<undef>"].contains(&id.to_lowercase().as_str()) {
+                        match binding_id(val) {
+                            Some(bid) => read_binding(bid, ctx, env),
+                            None => val.clone(),
+                        }
+                    } else if id.parse::<i64>().is_ok() || id.parse::<f64>().is_ok() {
+                        id.to_string()
+                    } else if env.fns.contains_key(id) {
+                        // 函数名作为值传递，例如 .>map(double)
                         id.to_string()
                     } else {
-                        panic!("Undefined identifier: '{}'", id)
+                        fail!("R074", id)
                     }
                 }
             }
         },
         Expr::Input(args) => {
             // 参数默认值
-            let prompt = if let Some(p) = args.get(0) {
-                eval_expr(p, ctx, fns)
+            let prompt = if let Some(p) = args.first() {
+                eval_expr(p, ctx, env)
             } else { "".to_string() };
 
             let in_type = if let Some(t) = args.get(1) {
                 match t {
                     Expr::Ident(s) => s.to_lowercase(),
-                    _ => eval_expr(t, ctx, fns).to_lowercase(),
+                    _ => eval_expr(t, ctx, env).to_lowercase(),
                 }
             } else {
                 "text".into()
             };
 
             let limit: usize = if let Some(lim) = args.get(2) {
-                eval_expr(lim, ctx, fns).parse::<usize>().unwrap_or(0)
+                eval_expr(lim, ctx, env).parse::<usize>().unwrap_or(0)
             } else { 0 };
 
             use std::io::{self, Write};
-            print!("{}", prompt);
+            print!("{}", display(&prompt));
             io::stdout().flush().ok();
 
             let mut buf = String::new();
             io::stdin().read_line(&mut buf).unwrap();
-            let mut input = buf.trim_end().to_string();
+            let mut input = buf.trim_end().replace(VALUE_TAG, "");
 
             if limit != 0 && input.len() > limit {
                 input.truncate(limit);
@@ -743,160 +3365,168 @@ fn eval_expr(
                 _ => input,
             }
         }
-        Expr::Call(name, args) => {
-            if let Some(f) = fns.get(name) {
-                let mut local_ctx = HashMap::new();
-                for (i, (pname, _ptype)) in f.params.iter().enumerate() {
-                    let arg_val = args.get(i).map(|e| eval_expr(e, ctx, fns)).unwrap_or_default();
-                    local_ctx.insert(pname.clone(), (arg_val, None, false));
-                }
-                match exec_with_ctx(f, &mut local_ctx, fns) {
-                    ExecResult::Return(v) => v,
-                    _ => "<void>".to_string()
-                }
+        Expr::Call(name, args, site) => {
+            if let Some(f) = env.fns.get(name) {
+                let passed = args.iter().map(|e| eval_expr(e, ctx, env)).collect();
+                run_function(f, bind_call(f, passed, *site, env), env)
+            } else if BUILTIN_METHODS.contains(&name.as_str()) {
+                let passed = args.iter().map(|e| eval_expr(e, ctx, env)).collect();
+                call_builtin(name, passed, env).unwrap_or_else(|| NULL.into())
             } else {
-                "<unknown-fn>".to_string()
-            }
-        }
-        Expr::Binary(lhs, op, rhs) => {
-            let l_str = eval_expr(lhs, ctx, fns);
-            let r_str = eval_expr(rhs, ctx, fns);
-            let lnum = l_str.parse::<f64>().unwrap_or(0.0);
-            let rnum = r_str.parse::<f64>().unwrap_or(0.0);
-            let result = match op.as_str() {
-                "+"  => format!("{}", lnum + rnum),
-                "-"  => format!("{}", lnum - rnum),
-                "*"  => format!("{}", lnum * rnum),
-                "/"  => format!("{}", if rnum != 0.0 { lnum / rnum } else { 0.0 }),
-                ">"  => (lnum >  rnum).to_string(),
-                "<"  => (lnum <  rnum).to_string(),
-                "="  => (l_str == r_str).to_string(),        // 如果单等号当作等于
-                "==" => (l_str == r_str).to_string(),
-                "<=" => (lnum <= rnum).to_string(),
-                ">=" => (lnum >= rnum).to_string(),
-                "&&" => {
-                    // 非零 / 非 "false" 视为真
-                    let lbool = !(l_str == "0" || l_str.eq_ignore_ascii_case("false"));
-                    let rbool = !(r_str == "0" || r_str.eq_ignore_ascii_case("false"));
-                    if lbool && rbool { "true".into() } else { "false".into() }
-                }
-                "||" => {
-                    let lbool = !(l_str == "0" || l_str.eq_ignore_ascii_case("false"));
-                    let rbool = !(r_str == "0" || r_str.eq_ignore_ascii_case("false"));
-                    if lbool || rbool { "true".into() } else { "false".into() }
-                }
-                _other => {
-                    "<bad-op>".to_string()
-                }
-            };
-            result
+                fallback(*site, "R015", &[name.to_string()], NULL, env)
+            }
+        }
+        Expr::Binary(lhs, op, rhs, site) => {
+            let l_str = eval_expr(lhs, ctx, env);
+            let r_str = eval_expr(rhs, ctx, env);
+            if let Some(result) = overloaded_binary(op, &l_str, &r_str, env) {
+                return result;
+            }
+            match op.as_str() {
+                "="  => return bool_value(l_str == r_str),  // 如果单等号当作等于
+                "==" => return bool_value(l_str == r_str),
+                "!=" => return bool_value(l_str != r_str),
+                "+" | "-" | "*" | "/" | ">" | "<" | "<=" | ">=" => {}
+                other => return fallback(*site, "R090", &[other.to_string()], "<bad-op>", env),
+            }
+            let lnum = as_number(&l_str, *site, env);
+            let rnum = as_number(&r_str, *site, env);
+            match op.as_str() {
+                "/"  if rnum == 0.0 => fallback(*site, "R091", &[], "0", env),
+                "+" | "-" | "*" | "/" => arithmetic(op, &l_str, &r_str, lnum, rnum),
+                ">"  => bool_value(lnum >  rnum),
+                "<"  => bool_value(lnum <  rnum),
+                "<=" => bool_value(lnum <= rnum),
+                _    => bool_value(lnum >= rnum),
+            }
+        }
+        Expr::Coalesce(left, right) => {
+            let l = eval_expr(left, ctx, env);
+            if l == NULL { eval_expr(right, ctx, env) } else { l }
+        }
+        Expr::SafeAccess(obj_expr, prop, site) => {
+            let obj = eval_expr(obj_expr, ctx, env);
+            if obj == NULL {
+                return obj;
+            }
+            eval_expr(&Expr::Access(Box::new(Expr::Str(obj)), prop.clone(), *site), ctx, env)
+        }
+        Expr::SafeMethodCall { target, name, args, site } => {
+            let recv = eval_expr(target, ctx, env);
+            if recv == NULL {
+                return recv;
+            }
+            let call = Expr::MethodCall { target: Box::new(Expr::Str(recv)), name: name.clone(), args: args.clone(), site: *site };
+            eval_expr(&call, ctx, env)
         }
         Expr::Logical(op, left, right) => {
-            let l = eval_expr(left, ctx, fns) == "true";
-            let r = eval_expr(right, ctx, fns) == "true";
+            let l = truthy(&eval_expr(left, ctx, env));
             match op.as_str() {
-                "&&" => (l && r).to_string(),
-                "||" => (l || r).to_string(),
-                _ => panic!("Unsupported logical operator: {}", op),
+                "&&" => bool_value(l && truthy(&eval_expr(right, ctx, env))),
+                "||" => bool_value(l || truthy(&eval_expr(right, ctx, env))),
+                _ => fail!("R075", op),
             }
         }
         Expr::Array(elements) => {
-            let values: Vec<String> = elements.iter().map(|e| eval_expr(e, ctx, fns)).collect();
-            format!("[{}]", values.join(","))
+            let values: Vec<String> = elements.iter().map(|e| eval_expr(e, ctx, env)).collect();
+            serialize_array_vec(&values)
         }
-        Expr::Index(array_expr, index_expr) => {
-            let target_str = eval_expr(array_expr, ctx, fns);
-            let key = eval_expr(index_expr, ctx, fns).trim_matches('"').to_string();
+        Expr::Index(array_expr, index_expr, site) => {
+            let target_str = eval_expr(array_expr, ctx, env);
+            let key = eval_expr(index_expr, ctx, env).trim_matches('"').to_string();
+            if target_str == NULL {
+                fail!("R076", key);
+            }
+            if let Some(r) = parse_range(&target_str) {
+                let idx = key.parse::<usize>().unwrap_or_else(|_| fail!("R077", key));
+                return r.get(idx).map(|n| n.to_string())
+                    .unwrap_or_else(|| fallback(*site, "R092", &[idx.to_string(), r.len().to_string()], NULL, env));
+            }
+            let target_str = match split_record(&target_str, env) {
+                Some((_, body)) => body.to_string(),
+                None => target_str,
+            };
 
             if target_str.starts_with('{') && target_str.ends_with('}') {
-                // Handle object-style index
-                let inner = &target_str[1..target_str.len()-1];
-                let mut map = HashMap::new();
-                let mut depth = 0;
-                let mut current = String::new();
-                let mut entries = vec![];
-
-                for c in inner.chars() {
-                    match c {
-                        '{' | '[' => {
-                            depth += 1;
-                            current.push(c);
-                        }
-                        '}' | ']' => {
-                            depth -= 1;
-                            current.push(c);
-                        }
-                        ',' if depth == 0 => {
-                            entries.push(current.trim().to_string());
-                            current.clear();
-                        }
-                        _ => current.push(c),
-                    }
-                }
-                if !current.trim().is_empty() {
-                    entries.push(current.trim().to_string());
-                }
-
-                for kv in entries {
-                    if let Some((k, v)) = kv.split_once(':') {
-                        let parsed_key = k.trim().trim_matches('"').to_string();
-                        let val = v.trim().to_string();
-                        map.insert(parsed_key, val);
-                    }
-                }
-
-                if let Some(value) = map.get(&key) {
-                    value.clone()
-                } else {
-                    // fallback: return "" instead of panic
-                    // println!("Key '{}' not found in object: {}", key, target_str);
-                    "".to_string()
+                let map = parse_object_string(&target_str);
+                match map.get(&key) {
+                    Some(value) => value.clone(),
+                    None => fallback(*site, "R093", &[key.to_string()], NULL, env),
                 }
             } else if target_str.starts_with('[') && target_str.ends_with(']') {
-                // Handle array-style index with nested structure parsing
-                let idx = key.parse::<usize>().unwrap_or_else(|_| panic!("Invalid index: '{}'", key));
-                let trimmed = target_str.trim_matches(&['[', ']'][..]);
-
-                let mut elements = vec![];
-                let mut current = String::new();
-                let mut depth = 0;
-
-                for c in trimmed.chars() {
-                    match c {
-                        '{' | '[' => {
-                            depth += 1;
-                            current.push(c);
-                        }
-                        '}' | ']' => {
-                            depth -= 1;
-                            current.push(c);
-                        }
-                        ',' if depth == 0 => {
-                            elements.push(current.trim().to_string());
-                            current.clear();
-                        }
-                        _ => current.push(c),
-                    }
+                let idx = key.parse::<usize>().unwrap_or_else(|_| fail!("R077", key));
+                let elements = parse_array_string(&target_str);
+                match elements.get(idx) {
+                    Some(el) => el.to_string(),
+                    None => fallback(*site, "R094", &[idx.to_string(), elements.len().to_string()], NULL, env),
                 }
-                if !current.trim().is_empty() {
-                    elements.push(current.trim().to_string());
-                }
-
-                if idx >= elements.len() {
-                    // fallback: return "" instead of panic
-                    // panic!("Array index out of bounds: {}", idx);
-                    "".to_string()
-                } else {
-                    elements[idx].to_string()
+            } else {
+                // 普通字符串按字素簇下标取字符
+                let idx = key.parse::<usize>().unwrap_or_else(|_| fail!("R077", key));
+                let chars = graphemes(&target_str);
+                match chars.get(idx) {
+                    Some(ch) => ch.to_string(),
+                    None => fallback(*site, "R095", &[idx.to_string(), chars.len().to_string()], NULL, env),
                 }
+            }
+        }
+        Expr::Slice(target, start, end, site) => {
+            let target_str = eval_expr(target, ctx, env);
+            if target_str == NULL {
+                fail!("R078");
+            }
+            let mut bound = |e: &Option<Box<Expr>>| e.as_ref().map(|e| {
+                let v = eval_expr(e, ctx, env);
+                v.parse::<usize>().unwrap_or_else(|_| fail!("R079", v))
+            });
+            let (start, end) = (bound(start), bound(end));
+            // 越界的端点截到长度，起点在终点之后时得到空值
+            let clamp = |len: usize| {
+                let hi = end.unwrap_or(len).min(len);
+                (start.unwrap_or(0).min(hi), hi)
+            };
+            if target_str.starts_with('[') && target_str.ends_with(']') {
+                let items = parse_array_string(&target_str);
+                let (lo, hi) = clamp(items.len());
+                serialize_array_vec(&items[lo..hi])
+            } else if target_str.starts_with('{') && target_str.ends_with('}') {
+                fallback(*site, "R096", &[target_str.to_string()], NULL, env)
             } else {
-                // fallback: return "" instead of panic
-                // panic!("Unsupported index target: {}", target_str)
-                "".to_string()
+                let chars = graphemes(&target_str);
+                let (lo, hi) = clamp(chars.len());
+                chars[lo..hi].concat()
             }
         }
-        Expr::Access(obj_expr, prop) => {
-            let obj_str = eval_expr(obj_expr, ctx, fns);
+        Expr::Access(obj_expr, prop, site) => {
+            // Enum.Variant without payload
+            if let Expr::Ident(base) = obj_expr.as_ref()
+                && !ctx.contains_key(base)
+                && let Some(decl) = env.enums.get(base) {
+                return make_variant(decl, prop, Vec::new());
+            }
+
+            let obj_str = eval_expr(obj_expr, ctx, env);
+            if obj_str == NULL {
+                fail!("R080", prop);
+            }
+
+            // payload field of an enum value: s.r for Shape.Circle(r)
+            if let Some((ename, variant, payload)) = split_variant(&obj_str, env) {
+                let fields = &env.enums[ename].variants.iter()
+                    .find(|(v, _)| v == variant)
+                    .expect("variant checked by split_variant").1;
+                return match fields.iter().position(|f| f == prop) {
+                    Some(i) => payload[i].clone(),
+                    None => fail!("R081", ename, variant, prop),
+                };
+            }
+
+            if let Some((tname, body)) = split_record(&obj_str, env) {
+                let fields = parse_object_string(body);
+                return fields.get(prop)
+                    .cloned()
+                    .unwrap_or_else(|| fail!("R011", tname, prop));
+            }
 
             // Support array.length
             if obj_str.starts_with('[') && obj_str.ends_with(']') && prop == "length" {
@@ -905,71 +3535,106 @@ fn eval_expr(
             }
 
             if obj_str.starts_with('{') && obj_str.ends_with('}') {
-                let inner = &obj_str[1..obj_str.len()-1];
-                let mut pairs = HashMap::new();
-                let mut depth = 0;
-                let mut current = String::new();
-                let mut entries = vec![];
-
-                for c in inner.chars() {
-                    match c {
-                        '{' | '[' => {
-                            depth += 1;
-                            current.push(c);
-                        }
-                        '}' | ']' => {
-                            depth -= 1;
-                            current.push(c);
-                        }
-                        ',' if depth == 0 => {
-                            entries.push(current.trim().to_string());
-                            current.clear();
-                        }
-                        _ => current.push(c),
-                    }
-                }
-                if !current.trim().is_empty() {
-                    entries.push(current.trim().to_string());
-                }
-
-                for kv in entries {
-                    if let Some((k, v)) = kv.split_once(':') {
-                        let key = k.trim().trim_matches('"').to_string();
-                        let val = v.trim().to_string();
-                        pairs.insert(key, val);
-                    }
-                }
-
-                if let Some(value) = pairs.get(prop) {
-                    value.clone()
-                } else {
-                    println!("Accessing property '{}' from object string: {}", prop, obj_str);
-                    println!("Parsed key-value pairs: {:?}", pairs);
-                    panic!("Property '{}' not found in object", prop)
+                let pairs = parse_object_string(&obj_str);
+                match pairs.get(prop) {
+                    Some(value) => value.clone(),
+                    None => fallback(*site, "R097", &[prop.to_string()], NULL, env),
                 }
             } else {
-                panic!("Not an object: {}", obj_str)
+                fail!("R082", obj_str)
             }
         }
         Expr::Object(pairs) => {
             let kvs: Vec<String> = pairs.iter()
-                .map(|(k, v)| format!("\"{}\":{}", k, eval_expr(v, ctx, fns)))
+                .map(|(k, v)| format!("{}:{}", quote_str(k), quote_item(&eval_expr(v, ctx, env))))
                 .collect();
             format!("{{{}}}", kvs.join(","))
         }
+        Expr::Variant(ename, variant, args) => {
+            let decl = *env.enums.get(ename)
+                .unwrap_or_else(|| fail!("R083", ename));
+            let payload = args.iter().map(|a| eval_expr(a, ctx, env)).collect();
+            make_variant(decl, variant, payload)
+        }
+        Expr::Is(value, ename, variant, bindings) => {
+            let decl = *env.enums.get(ename)
+                .unwrap_or_else(|| fail!("R083", ename));
+            let arity = match decl.variants.iter().find(|(v, _)| v == variant) {
+                Some((_, fields)) => fields.len(),
+                None => fail!("R025", ename, variant),
+            };
+            if !bindings.is_empty() && bindings.len() != arity {
+                fail!("R084", ename, variant, bindings.len(), arity);
+            }
+            let val = eval_expr(value, ctx, env);
+            match split_variant(&val, env) {
+                Some((e, v, payload)) if e == ename && v == variant => {
+                    // 匹配成功时把载荷绑定到变量上，供分支体使用
+                    for (name, pv) in bindings.iter().zip(payload) {
+                        if name != "_" {
+                            let t = infer_type(&pv, env);
+                            ctx.insert(name.clone(), (pv, t, false));
+                            env.invalidate(name, ctx);
+                        }
+                    }
+                    "true".to_string()
+                }
+                _ => "false".to_string(),
+            }
+        }
+        Expr::Range { start, end, inclusive, step, site } => {
+            let bound = |e: &Expr, ctx: &mut HashMap<String, (String, Option<String>, bool)>, what: &str| {
+                let v = eval_expr(e, ctx, env);
+                v.parse::<i64>().unwrap_or_else(|_| fail!("R085", what, v))
+            };
+            let s = bound(start, ctx, "start");
+            let e = bound(end, ctx, "end");
+            let st = step.as_ref().map(|st| bound(st, ctx, "step"));
+            if st == Some(0) {
+                panic!("{}", messages::error("R024", Some(site_position(*site)), &[]));
+            }
+            Range::new(s, e, *inclusive, st).to_value()
+        }
+        Expr::Construct(tname, fields) => {
+            let decl = *env.types.get(tname)
+                .unwrap_or_else(|| fail!("R039", tname));
+            let mut map = HashMap::new();
+            for (field, value) in fields {
+                let val = eval_expr(value, ctx, env);
+                let val = check_field(decl, field, val, env);
+                if map.insert(field.clone(), val).is_some() {
+                    fail!("R086", field, tname);
+                }
+            }
+            for (field, _) in &decl.fields {
+                if !map.contains_key(field) {
+                    fail!("R087", field, tname);
+                }
+            }
+            serialize_record(decl, &map)
+        }
     }
 }
 
-fn infer_type(val: &str) -> Option<String> {
-    if val.parse::<i64>().is_ok() {
+fn infer_type(val: &str, env: &Env) -> Option<String> {
+    if let Some((tname, _)) = split_record(val, env) {
+        Some(tname.to_string())
+    } else if let Some((ename, _, _)) = split_variant(val, env) {
+        Some(ename.to_string())
+    } else if parse_range(val).is_some() {
+        Some("range".to_string())
+    } else if lazy_id(val).is_some() {
+        Some("iter".to_string())
+    } else if val == NULL {
+        Some("null".to_string())
+    } else if val.parse::<i64>().is_ok() {
         Some("int".to_string())
     } else if val.parse::<f64>().is_ok() {
         Some("float".to_string())
     } else if val.to_lowercase() == "true" || val.to_lowercase() == "false" {
         Some("bool".to_string())
-    } else if val.trim().starts_with('[') && val.trim().ends_with(']') {
-        Some("obj".to_string())
-    } else if val.trim().starts_with('{') && val.trim().ends_with('}') {
+    } else if (val.trim().starts_with('[') && val.trim().ends_with(']'))
+        || (val.trim().starts_with('{') && val.trim().ends_with('}')) {
         Some("obj".to_string())
     } else {
         Some("string".to_string())
@@ -1,3 +1,6 @@
+use crate::unicode;
+use crate::messages;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub kind: TokenKind,
@@ -10,9 +13,13 @@ pub enum TokenKind {
     Fn,
     Return,
     Loop,
+    Break,
+    Yield,
+    Type,
+    Enum,
+    Interface,
     Pipe,
     BindOne,
-    #[allow(dead_code)]
     BindTwo,
     Assign,
     ForceAssign,
@@ -22,30 +29,179 @@ pub enum TokenKind {
     LBracket, RBracket,
     Comma, Eq,
 
+    // 运算符：按最长匹配切分，`<=` 是一个 Le 而不是 '<' 加 '='
+    Plus, PlusPlus,
+    Minus, MinusMinus,
+    Star, Slash,
+    Lt, Le,
+    Gt, Ge,
+    EqEq,
+    BangEq,
+    Bang,
+    Amp, AndAnd,
+    Bar, OrOr,
+    QuestionDot,       // ?.
+    QuestionPipe,      // ?.>
+    QuestionQuestion,  // ??
+
     Colon,
     Dot,
+    DotDot,
+    DotDotEq,
 
     Ident(String),
-    Number(f64),
+    Number(NumLit),
     Str(String),
 
     Comment(String),
+    DocComment(String),   // `/// ...`，挂到后面的 F> 上
     Whitespace,
-    Unknown(char),
+    Unknown(char),     // 无法识别的字符，解析时报错
 
     If,
     Elif,
     Else,
-    
-    #[allow(dead_code)]
+
     Semicolon,
 }
 
-pub fn tokenize(input: &str) -> Vec<Token> {
+// Token 的源码写法，用于诊断信息
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let text = match self {
+            TokenKind::Fn => "F>",
+            TokenKind::Return => "R>",
+            TokenKind::Loop => "L>",
+            TokenKind::Break => "B>",
+            TokenKind::Yield => "Y>",
+            TokenKind::Type => "T>",
+            TokenKind::Enum => "E>",
+            TokenKind::Interface => "P>",
+            TokenKind::Pipe => ".>",
+            TokenKind::BindOne => "=>",
+            TokenKind::BindTwo => "<=>",
+            TokenKind::Assign => ":=",
+            TokenKind::ForceAssign => "=::",
+            TokenKind::LParen => "(",
+            TokenKind::RParen => ")",
+            TokenKind::LBrace => "{",
+            TokenKind::RBrace => "}",
+            TokenKind::LBracket => "[",
+            TokenKind::RBracket => "]",
+            TokenKind::Comma => ",",
+            TokenKind::Eq => "=",
+            TokenKind::Plus => "+",
+            TokenKind::PlusPlus => "++",
+            TokenKind::Minus => "-",
+            TokenKind::MinusMinus => "--",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Lt => "<",
+            TokenKind::Le => "<=",
+            TokenKind::Gt => ">",
+            TokenKind::Ge => ">=",
+            TokenKind::EqEq => "==",
+            TokenKind::BangEq => "!=",
+            TokenKind::Bang => "!",
+            TokenKind::Amp => "&",
+            TokenKind::AndAnd => "&&",
+            TokenKind::Bar => "|",
+            TokenKind::OrOr => "||",
+            TokenKind::QuestionDot => "?.",
+            TokenKind::QuestionPipe => "?.>",
+            TokenKind::QuestionQuestion => "??",
+            TokenKind::Colon => ":",
+            TokenKind::Dot => ".",
+            TokenKind::DotDot => "..",
+            TokenKind::DotDotEq => "..=",
+            TokenKind::Ident(name) => return write!(f, "{}", name),
+            TokenKind::Number(n) => return write!(f, "{}", n),
+            TokenKind::Str(s) => return write!(f, "{:?}", s),
+            TokenKind::Comment(c) => return write!(f, "//{}", c),
+            TokenKind::DocComment(d) => return write!(f, "///{}", d),
+            TokenKind::Whitespace => " ",
+            TokenKind::Unknown(c) => return write!(f, "{}", c),
+            TokenKind::If => "if",
+            TokenKind::Elif => "elif",
+            TokenKind::Else => "else",
+            TokenKind::Semicolon => ";",
+        };
+        f.write_str(text)
+    }
+}
+
+// 数字字面量：不带小数点与指数的写法是整数，其余为浮点数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumLit {
+    Int(i64),
+    Float(f64),
+}
+
+impl std::ops::Neg for NumLit {
+    type Output = NumLit;
+    fn neg(self) -> NumLit {
+        match self {
+            NumLit::Int(i) => i.checked_neg().map_or(NumLit::Float(-(i as f64)), NumLit::Int),
+            NumLit::Float(f) => NumLit::Float(-f),
+        }
+    }
+}
+
+impl std::fmt::Display for NumLit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NumLit::Int(i) => write!(f, "{}", i),
+            // 浮点数总带小数部分，1.0 不会被当成整数 1
+            NumLit::Float(x) if x.is_finite() && x.fract() == 0.0 => write!(f, "{}.0", x),
+            NumLit::Float(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+// 本地化关键字：单词形式（如果 → if）与带 `>` 的箭头形式（函数> → F>）。
+// 启用后英文拼写仍然可用，两种写法得到相同的 TokenKind
+struct KeywordAliases {
+    words: &'static [(&'static str, &'static str)],
+    arrows: &'static [(&'static str, &'static str)],
+}
+
+const ZH_KEYWORDS: KeywordAliases = KeywordAliases {
+    words: &[
+        ("如果", "if"), ("否则如果", "elif"), ("否则", "else"),
+        ("真", "true"), ("假", "false"), ("空", "null"),
+    ],
+    arrows: &[
+        ("函数", "F"), ("返回", "R"), ("循环", "L"), ("跳出", "B"), ("产出", "Y"),
+        ("类型", "T"), ("枚举", "E"), ("接口", "P"),
+    ],
+};
+
+pub const LANGUAGES: [&str; 2] = ["en", "zh"];
+
+fn keyword_aliases(lang: &str) -> Option<&'static KeywordAliases> {
+    match lang {
+        "zh" => Some(&ZH_KEYWORDS),
+        _ => None,
+    }
+}
+
+// 解释器内部值（null 等）的标记字符。U+FDD0 是 Unicode 留给程序内部使用的非字符，
+// 词法分析器不会产生它：源码中出现即报错，\u{FDD0} 也不是合法转义
+pub const VALUE_TAG: char = '\u{FDD0}';
+
+// lang 来自 --keywords；文件头的 `//! lang zh` 也会启用对应的关键字
+pub fn tokenize(input: &str, lang: Option<&str>) -> Vec<Token> {
+    for (i, text) in input.lines().enumerate() {
+        if let Some(pos) = text.find(VALUE_TAG) {
+            let col = text[..pos].chars().count() + 1;
+            panic!("{}", messages::error("L011", Some((i + 1, col)), &[]));
+        }
+    }
     let mut tokens = Vec::new();
     let mut chars = input.chars().peekable();
     let mut line = 1;
     let mut col = 1;
+    let mut aliases = lang.and_then(keyword_aliases);
 
     while let Some(&c) = chars.peek() {
         let token_line = line;
@@ -64,6 +220,7 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 col += 1;
                 match chars.peek() {
                     Some('>') => { chars.next(); tokens.push(Token { kind: TokenKind::BindOne, line: token_line, col: token_col }); col += 1; }
+                    Some('=') => { chars.next(); tokens.push(Token { kind: TokenKind::EqEq, line: token_line, col: token_col }); col += 1; }
                     Some(':') => {
                         chars.next();
                         col += 1;
@@ -89,16 +246,40 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                     tokens.push(Token { kind: TokenKind::Colon, line: token_line, col: token_col });
                 }
             }
-            '<' => {
-                chars.next(); col += 1;
-                if chars.peek() == Some(&'=') {
-                    // Emit '<' then '=' as separate tokens for <=
-                    tokens.push(Token { kind: TokenKind::Unknown('<'), line: token_line, col: token_col });
-                    chars.next(); col += 1;
-                    tokens.push(Token { kind: TokenKind::Eq,      line: token_line, col: token_col + 1 });
-                } else {
-                    tokens.push(Token { kind: TokenKind::Unknown('<'), line: token_line, col: token_col });
+            // `.5` 只在前一个 token 不能结束表达式时才是数字，a.5、x..5 仍按点号处理
+            // 不能结束表达式的位置上，'-' 紧跟数字时并入字面量，-9223372036854775808 才写得出来
+            c if c.is_ascii_digit() || ((c == '.' || c == '-') && !ends_expr(&tokens) && {
+                let mut ahead = chars.clone();
+                ahead.next();
+                ahead.peek().is_some_and(|d| d.is_ascii_digit())
+            }) => {
+                let mut raw = String::new();
+                raw.push(c);
+                chars.next();
+                col += 1;
+                // 先把整个字面量（连同紧跟的字母、数字）读完，再统一校验，1.2.3、0b102、3px 都报错
+                while let Some(&nc) = chars.peek() {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    let digit_next = ahead.peek().is_some_and(|d| d.is_ascii_digit());
+                    let take = match nc {
+                        // '.' 后面是数字才属于数字（1..10、2.>f 不受影响）
+                        '.' => digit_next,
+                        // 指数符号 1e-9；十六进制里的 e 是数字，0x1e-5 是减法
+                        '+' | '-' => digit_next && raw.ends_with(['e', 'E']) && radix_prefix(&raw).is_none(),
+                        _ => is_ident_continue(nc),
+                    };
+                    if !take {
+                        break;
+                    }
+                    raw.push(nc);
+                    chars.next();
+                    col += 1;
                 }
+                let number = parse_number(&raw).unwrap_or_else(|(code, args)| {
+                    panic!("{}", messages::error(code, Some((token_line, token_col)), &args))
+                });
+                tokens.push(Token { kind: TokenKind::Number(number), line: token_line, col: token_col });
             }
             '.' => {
                 chars.next();
@@ -107,49 +288,45 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                     chars.next();
                     col += 1;
                     tokens.push(Token { kind: TokenKind::Pipe, line: token_line, col: token_col });
+                } else if chars.peek() == Some(&'.') {
+                    chars.next();
+                    col += 1;
+                    if chars.peek() == Some(&'=') {
+                        chars.next();
+                        col += 1;
+                        tokens.push(Token { kind: TokenKind::DotDotEq, line: token_line, col: token_col });
+                    } else {
+                        tokens.push(Token { kind: TokenKind::DotDot, line: token_line, col: token_col });
+                    }
                 } else {
                     tokens.push(Token { kind: TokenKind::Dot, line: token_line, col: token_col });
                 }
             }
-            '+' => { tokens.push(Token { kind: TokenKind::Unknown('+'), line: token_line, col: token_col }); chars.next(); col += 1; },
-            '-' => { tokens.push(Token { kind: TokenKind::Unknown('-'), line: token_line, col: token_col }); chars.next(); col += 1; },
-            '*' => { tokens.push(Token { kind: TokenKind::Unknown('*'), line: token_line, col: token_col }); chars.next(); col += 1; },
-            '>' => {
-                chars.next(); col += 1;
-                if chars.peek() == Some(&'=') {
-                    tokens.push(Token { kind: TokenKind::Unknown('>'), line: token_line, col: token_col });
-                    chars.next(); col += 1;
-                    tokens.push(Token { kind: TokenKind::Eq,      line: token_line, col: token_col + 1 });
-                } else {
-                    tokens.push(Token { kind: TokenKind::Unknown('>'), line: token_line, col: token_col });
-                }
-            }
-            '!' => {
-                tokens.push(Token {
-                    kind: TokenKind::Unknown('!'),
-                    line: token_line,
-                    col: token_col,
-                });
-                chars.next();
-                col += 1;
-            }
-            '&' => {
-                tokens.push(Token {
-                    kind: TokenKind::Unknown('&'),
-                    line: token_line,
-                    col: token_col,
-                });
-                chars.next();
-                col += 1;
-            }
-            '|' => {
-                tokens.push(Token {
-                    kind: TokenKind::Unknown('|'),
-                    line: token_line,
-                    col: token_col,
-                });
+            '<' | '>' | '+' | '-' | '*' | '!' | '&' | '|' | '?' | ';' => {
                 chars.next();
                 col += 1;
+                let kind = match c {
+                    '<' => munch(&mut chars, &mut col, &[("=>", TokenKind::BindTwo), ("=", TokenKind::Le)], TokenKind::Lt),
+                    '>' => munch(&mut chars, &mut col, &[("=", TokenKind::Ge)], TokenKind::Gt),
+                    '+' => munch(&mut chars, &mut col, &[("+", TokenKind::PlusPlus)], TokenKind::Plus),
+                    // `--` 只在标识符之后（a--）或表达式开头（--a）成为自减，5--3 仍是 5 - -3
+                    '-' if matches!(last_significant(&tokens), Some(TokenKind::Ident(_))) || !ends_expr(&tokens) => {
+                        munch(&mut chars, &mut col, &[("-", TokenKind::MinusMinus)], TokenKind::Minus)
+                    }
+                    '-' => TokenKind::Minus,
+                    '&' => munch(&mut chars, &mut col, &[("&", TokenKind::AndAnd)], TokenKind::Amp),
+                    '|' => munch(&mut chars, &mut col, &[("|", TokenKind::OrOr)], TokenKind::Bar),
+                    // 单独的 '?' 没有含义
+                    '?' => munch(&mut chars, &mut col, &[
+                        ("?", TokenKind::QuestionQuestion),
+                        (".>", TokenKind::QuestionPipe),
+                        (".", TokenKind::QuestionDot),
+                    ], TokenKind::Unknown('?')),
+                    '*' => TokenKind::Star,
+                    '!' => munch(&mut chars, &mut col, &[("=", TokenKind::BangEq)], TokenKind::Bang),
+                    _ => TokenKind::Semicolon,
+                };
+                tokens.push(Token { kind, line: token_line, col: token_col });
             }
             '/' => {
                 chars.next();
@@ -164,52 +341,71 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                         chars.next();
                         col += 1;
                     }
+                    if let Some(code) = comment.strip_prefix('!').and_then(|p| p.trim().strip_prefix("lang "))
+                        && tokens.iter().all(|t: &Token| matches!(t.kind, TokenKind::Comment(_) | TokenKind::Whitespace)) {
+                        let code = code.trim();
+                        if !LANGUAGES.contains(&code) {
+                            panic!("{}", messages::error("L001", Some((token_line, token_col)), &[code.to_string()]));
+                        }
+                        aliases = keyword_aliases(code);
+                    }
+                    // `///` 是文档注释，`////` 仍是普通注释
+                    let kind = match comment.strip_prefix('/') {
+                        Some(doc) if !doc.starts_with('/') => {
+                            TokenKind::DocComment(doc.strip_prefix(' ').unwrap_or(doc).to_string())
+                        }
+                        _ => TokenKind::Comment(comment),
+                    };
+                    tokens.push(Token { kind, line: token_line, col: token_col });
+                } else if chars.peek() == Some(&'*') {
+                    // 块注释可以嵌套：/* 外 /* 内 */ 外 */
+                    chars.next();
+                    col += 1;
+                    let mut depth = 1;
+                    let mut comment = String::new();
+                    while depth > 0 {
+                        let Some(nc) = chars.next() else {
+                            panic!("{}", messages::error("L009", Some((token_line, token_col)), &[]));
+                        };
+                        if nc == '\n' {
+                            line += 1;
+                            col = 1;
+                        } else {
+                            col += 1;
+                        }
+                        let opens = nc == '/' && chars.peek() == Some(&'*');
+                        let closes = nc == '*' && chars.peek() == Some(&'/');
+                        if opens || closes {
+                            chars.next();
+                            col += 1;
+                            depth = if opens { depth + 1 } else { depth - 1 };
+                            if depth == 0 {
+                                break;
+                            }
+                            comment.push(nc);
+                            comment.push(if opens { '*' } else { '/' });
+                        } else {
+                            comment.push(nc);
+                        }
+                    }
                     tokens.push(Token { kind: TokenKind::Comment(comment), line: token_line, col: token_col });
                 } else {
-                    tokens.push(Token { kind: TokenKind::Unknown('/'), line: token_line, col: token_col });
+                    tokens.push(Token { kind: TokenKind::Slash, line: token_line, col: token_col });
                 }
             }
+            // "..."、r"..." 与 """...""" 都在 read_string 里读完，出错时指向开头的引号
             '"' => {
-                // Parse string literal with backslash escapes
+                let value = read_string(&mut chars, false, &mut line, &mut col).unwrap_or_else(|(code, args)| {
+                    panic!("{}", messages::error(code, Some((token_line, token_col)), &args))
+                });
+                tokens.push(Token { kind: TokenKind::Str(value), line: token_line, col: token_col });
+            }
+            'r' if chars.clone().nth(1) == Some('"') => {
                 chars.next();
                 col += 1;
-                let mut value = String::new();
-                while let Some(&nc) = chars.peek() {
-                    if nc == '"' {
-                        // closing quote
-                        chars.next();
-                        col += 1;
-                        break;
-                    }
-                    if nc == '\\' {
-                        // escape sequence
-                        chars.next();
-                        col += 1;
-                        if let Some(&esc) = chars.peek() {
-                            let escaped_char = match esc {
-                                'n' => '\n',
-                                't' => '\t',
-                                'r' => '\r',
-                                '\\' => '\\',
-                                '"' => '"',
-                                other => other,
-                            };
-                            value.push(escaped_char);
-                            chars.next();
-                            col += 1;
-                        }
-                        continue;
-                    }
-                    // regular character
-                    value.push(nc);
-                    chars.next();
-                    if nc == '\n' {
-                        line += 1;
-                        col = 1;
-                    } else {
-                        col += 1;
-                    }
-                }
+                let value = read_string(&mut chars, true, &mut line, &mut col).unwrap_or_else(|(code, args)| {
+                    panic!("{}", messages::error(code, Some((token_line, token_col)), &args))
+                });
                 tokens.push(Token { kind: TokenKind::Str(value), line: token_line, col: token_col });
             }
             c if c.is_whitespace() => {
@@ -222,19 +418,6 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                 }
                 tokens.push(Token { kind: TokenKind::Whitespace, line: token_line, col: token_col });
             }
-            c if c.is_ascii_digit() => {
-                let mut number = String::new();
-                while let Some(&nc) = chars.peek() {
-                    if nc.is_ascii_digit() || nc == '.' {
-                        number.push(nc);
-                        chars.next();
-                        col += 1;
-                    } else {
-                        break;
-                    }
-                }
-                tokens.push(Token { kind: TokenKind::Number(number.parse().unwrap()), line: token_line, col: token_col });
-            }
             c if is_ident_start(c) => {
                 let mut ident = String::new();
                 ident.push(c);
@@ -249,6 +432,17 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                         break;
                     }
                 }
+                let mut ident = unicode::identifier(&ident).unwrap_or_else(|code| {
+                    panic!("{}", messages::error(code, Some((token_line, token_col)), &[ident.clone()]))
+                });
+                if let Some(aliases) = aliases {
+                    let arrow = chars.peek() == Some(&'>');
+                    if let Some((_, en)) = aliases.words.iter()
+                        .chain(aliases.arrows.iter().filter(|_| arrow))
+                        .find(|(local, _)| *local == ident) {
+                        ident = en.to_string();
+                    }
+                }
 
                 match ident.as_str() {
                     "F" => {
@@ -263,6 +457,26 @@ pub fn tokenize(input: &str) -> Vec<Token> {
                         if chars.peek() == Some(&'>') { chars.next(); col += 1; tokens.push(Token { kind: TokenKind::Loop, line: token_line, col: token_col }); }
                         else { tokens.push(Token { kind: TokenKind::Ident(ident), line: token_line, col: token_col }); }
                     }
+                    "B" => {
+                        if chars.peek() == Some(&'>') { chars.next(); col += 1; tokens.push(Token { kind: TokenKind::Break, line: token_line, col: token_col }); }
+                        else { tokens.push(Token { kind: TokenKind::Ident(ident), line: token_line, col: token_col }); }
+                    }
+                    "Y" => {
+                        if chars.peek() == Some(&'>') { chars.next(); col += 1; tokens.push(Token { kind: TokenKind::Yield, line: token_line, col: token_col }); }
+                        else { tokens.push(Token { kind: TokenKind::Ident(ident), line: token_line, col: token_col }); }
+                    }
+                    "T" => {
+                        if chars.peek() == Some(&'>') { chars.next(); col += 1; tokens.push(Token { kind: TokenKind::Type, line: token_line, col: token_col }); }
+                        else { tokens.push(Token { kind: TokenKind::Ident(ident), line: token_line, col: token_col }); }
+                    }
+                    "E" => {
+                        if chars.peek() == Some(&'>') { chars.next(); col += 1; tokens.push(Token { kind: TokenKind::Enum, line: token_line, col: token_col }); }
+                        else { tokens.push(Token { kind: TokenKind::Ident(ident), line: token_line, col: token_col }); }
+                    }
+                    "P" => {
+                        if chars.peek() == Some(&'>') { chars.next(); col += 1; tokens.push(Token { kind: TokenKind::Interface, line: token_line, col: token_col }); }
+                        else { tokens.push(Token { kind: TokenKind::Ident(ident), line: token_line, col: token_col }); }
+                    }
                     "if" => tokens.push(Token { kind: TokenKind::If, line: token_line, col: token_col }),
                     "elif" => tokens.push(Token { kind: TokenKind::Elif, line: token_line, col: token_col }),
                     "else" => tokens.push(Token { kind: TokenKind::Else, line: token_line, col: token_col }),
@@ -280,21 +494,275 @@ pub fn tokenize(input: &str) -> Vec<Token> {
     tokens.into_iter().filter(|t| t.kind != TokenKind::Whitespace).collect()
 }
 
-// ✅ 支持任意 Unicode 起始字符（中文、日文、emoji、国旗…）
+// 文件头的 `//! strict` 之类的编译指示：只看第一条代码之前的注释
+pub fn pragmas(tokens: &[Token]) -> Vec<String> {
+    tokens.iter()
+        .take_while(|t| matches!(t.kind, TokenKind::Comment(_) | TokenKind::Whitespace))
+        .filter_map(|t| match &t.kind {
+            TokenKind::Comment(text) => text.strip_prefix('!').map(|p| p.trim().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+// 最长匹配：首字符已读入，`longer` 按从长到短给出可能的后续字符及对应 token
+fn munch(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    col: &mut usize,
+    longer: &[(&str, TokenKind)],
+    single: TokenKind,
+) -> TokenKind {
+    for (rest, kind) in longer {
+        if chars.clone().take(rest.chars().count()).eq(rest.chars()) {
+            for _ in rest.chars() {
+                chars.next();
+                *col += 1;
+            }
+            return kind.clone();
+        }
+    }
+    single
+}
+
+// 从开头的引号读到结束引号。raw 时反斜杠没有特殊含义；三引号可以跨行，
+// 并去掉各行的公共缩进（结束的 """ 所在行也计入）
+fn read_string(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    raw: bool,
+    line: &mut usize,
+    col: &mut usize,
+) -> Result<String, (&'static str, Vec<String>)> {
+    let mut advance = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        let c = chars.next()?;
+        if c == '\n' {
+            *line += 1;
+            *col = 1;
+        } else {
+            *col += 1;
+        }
+        Some(c)
+    };
+    let closes_triple = |chars: &std::iter::Peekable<std::str::Chars>| chars.clone().take(2).eq(['"', '"']);
+
+    advance(chars);
+    let triple = closes_triple(chars);
+    if triple {
+        advance(chars);
+        advance(chars);
+    }
+    let mut body = String::new();
+    loop {
+        let Some(c) = advance(chars) else { return Err(("L007", vec![])) };
+        match c {
+            '"' if !triple => break,
+            '"' if closes_triple(chars) => {
+                advance(chars);
+                advance(chars);
+                break;
+            }
+            // 转义留到最后统一处理，这里只保证 \" 不会结束字符串
+            '\\' if !raw => {
+                body.push(c);
+                match advance(chars) {
+                    Some(next) => body.push(next),
+                    None => return Err(("L007", vec![])),
+                }
+            }
+            _ => body.push(c),
+        }
+    }
+    let body = if triple { dedent(&body) } else { body };
+    if raw { Ok(body) } else { unescape(&body) }
+}
+
+// 三引号字符串：去掉紧跟开头引号的空行与结束引号前的空白行，再去掉公共缩进
+fn dedent(body: &str) -> String {
+    let mut lines: Vec<&str> = body.split('\n').collect();
+    if lines.len() > 1 && lines[0].trim().is_empty() {
+        lines.remove(0);
+    }
+    let indent_of = |l: &str| l.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+    let closing = match lines.last() {
+        Some(last) if lines.len() > 1 && last.trim().is_empty() => Some(indent_of(last)),
+        _ => None,
+    };
+    if closing.is_some() {
+        lines.pop();
+    }
+    let indent = lines.iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indent_of(l))
+        .chain(closing)
+        .min()
+        .unwrap_or(0);
+    lines.iter()
+        .map(|l| l.chars().skip(indent).collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// \n \t \r \0 \\ \" \' 以及 \x41（ASCII）与 \u{1F680}（任意 Unicode 标量值）
+fn unescape(body: &str) -> Result<String, (&'static str, Vec<String>)> {
+    let mut out = String::new();
+    let mut it = body.chars().peekable();
+    while let Some(c) = it.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let Some(e) = it.next() else { break };
+        let mut seq = format!("\\{}", e);
+        let decoded = match e {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' | '"' | '\'' => Some(e),
+            'x' => {
+                let hex: String = it.by_ref().take(2).collect();
+                seq.push_str(&hex);
+                u8::from_str_radix(&hex, 16).ok()
+                    .filter(|b| hex.len() == 2 && b.is_ascii())
+                    .map(char::from)
+            }
+            'u' if it.peek() == Some(&'{') => {
+                let mut hex = String::new();
+                for h in it.by_ref() {
+                    seq.push(h);
+                    if h == '}' {
+                        break;
+                    }
+                    hex.push(h);
+                }
+                let hex = hex.trim_start_matches('{');
+                u32::from_str_radix(hex, 16).ok()
+                    .filter(|_| (1..=6).contains(&hex.len()) && seq.ends_with('}'))
+                    .and_then(char::from_u32)
+                    .filter(|&ch| ch != VALUE_TAG)
+            }
+            _ => None,
+        };
+        match decoded {
+            Some(ch) => out.push(ch),
+            None => return Err(("L008", vec![seq])),
+        }
+    }
+    Ok(out)
+}
+
+// 跳过空白与注释后的前一个 token
+fn last_significant(tokens: &[Token]) -> Option<&TokenKind> {
+    tokens.iter().rev()
+        .find(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment(_) | TokenKind::DocComment(_)))
+        .map(|t| &t.kind)
+}
+
+// 前一个有效 token 能否结束一个表达式（决定 `.5` 是不是数字）
+fn ends_expr(tokens: &[Token]) -> bool {
+    matches!(
+        last_significant(tokens),
+        Some(TokenKind::Ident(_) | TokenKind::Number(_) | TokenKind::Str(_)
+            | TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace)
+    )
+}
+
+// 0x/0o/0b 前缀（大小写均可）对应的进制，忽略字面量开头的负号
+fn radix_prefix(raw: &str) -> Option<u32> {
+    match raw.trim_start_matches('-').get(..2)?.to_ascii_lowercase().as_str() {
+        "0x" => Some(16),
+        "0o" => Some(8),
+        "0b" => Some(2),
+        _ => None,
+    }
+}
+
+// 数字字面量：0x/0o/0b 整数、_ 分隔符（只能夹在两个数字之间）、小数、指数与 .5；
+// 出错时返回诊断编号与参数
+fn parse_number(raw: &str) -> Result<NumLit, (&'static str, Vec<String>)> {
+    let malformed = || ("L004", vec![raw.to_string()]);
+    let (sign, unsigned) = match raw.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", raw),
+    };
+    let (radix, body) = match radix_prefix(raw) {
+        Some(radix) => (radix, &unsigned[2..]),
+        None => (10, unsigned),
+    };
+    let chars: Vec<char> = body.chars().collect();
+    for (i, &c) in chars.iter().enumerate() {
+        let between_digits = i > 0
+            && chars[i - 1].is_digit(radix)
+            && chars.get(i + 1).is_some_and(|n| n.is_digit(radix));
+        if c == '_' && !between_digits {
+            return Err(malformed());
+        }
+    }
+    let digits: String = sign.chars().chain(body.chars().filter(|&c| c != '_')).collect();
+    let out_of_range = || ("L006", vec![raw.to_string()]);
+
+    if radix != 10 {
+        if digits.len() == sign.len() {
+            return Err(malformed());
+        }
+        if let Some(bad) = digits[sign.len()..].chars().find(|c| !c.is_digit(radix)) {
+            return Err(("L005", vec![bad.to_string(), radix.to_string(), raw.to_string()]));
+        }
+        return i64::from_str_radix(&digits, radix).map(NumLit::Int).map_err(|_| out_of_range());
+    }
+
+    // 十进制：整数部分 [. 小数部分] [e [+-] 指数]
+    let all_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let (mantissa, exponent) = match digits[sign.len()..].split_once(['e', 'E']) {
+        Some((m, e)) => (m, Some(e.strip_prefix(['+', '-']).unwrap_or(e))),
+        None => (&digits[sign.len()..], None),
+    };
+    let (int_part, fraction) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (mantissa, None),
+    };
+    let valid = (all_digits(int_part) || (int_part.is_empty() && fraction.is_some()))
+        && fraction.is_none_or(all_digits)
+        && exponent.is_none_or(all_digits);
+    if !valid {
+        return Err(malformed());
+    }
+    if fraction.is_none() && exponent.is_none() {
+        digits.parse().map(NumLit::Int).map_err(|_| out_of_range())
+    } else {
+        match digits.parse::<f64>() {
+            Ok(f) if f.is_infinite() => Err(("L012", vec![raw.to_string()])),
+            parsed => parsed.map(NumLit::Float).map_err(|_| malformed()),
+        }
+    }
+}
+
+// ✅ 标识符遵循 UAX #31（XID_Start / XID_Continue），另外允许 `_` 与 emoji 序列
+//    （中文、日文、é、👨‍👩‍👧、🇨🇳…）；读入后统一做 NFC 规范化
 fn is_ident_start(c: char) -> bool {
-    // 不包括控制字符、符号、空格、标点等
-    !(c.is_control() || c.is_whitespace() || is_reserved_symbol(c))
+    unicode::is_identifier_start(c)
 }
 
-// ✅ 支持继续构建标识符
 fn is_ident_continue(c: char) -> bool {
-    !(c.is_control() || c.is_whitespace() || is_reserved_symbol(c) || matches!(c, '+' | '-' | '*' | '/' | '=' | '>' | '<' | ':' | ','))
+    unicode::is_identifier_continue(c)
 }
 
-// ✅ 屏蔽不能作为标识符的结构符号
-fn is_reserved_symbol(c: char) -> bool {
-    matches!(c,
-        '(' | ')' | '{' | '}' | '[' | ']' |
-        ',' | '=' | ':' | '<' | '>' | '.' | '/' | '"' | ';'
-    )
+// 同一文件里外形相同但写法不同的标识符（如拉丁 a 与西里尔 а），每对报告一次
+pub fn confusable_warnings(tokens: &[Token]) -> Vec<String> {
+    let mut seen: Vec<(String, &str, &Token)> = Vec::new(); // (skeleton, name, first use)
+    let mut warnings = Vec::new();
+    for tok in tokens {
+        let TokenKind::Ident(name) = &tok.kind else { continue };
+        if seen.iter().any(|(_, n, _)| n == name) {
+            continue;
+        }
+        let skel = unicode::skeleton(name);
+        if let Some((_, other, at)) = seen.iter().find(|(s, _, _)| *s == skel) {
+            warnings.push(messages::warning("W002", &[
+                name.clone(), tok.line.to_string(), tok.col.to_string(),
+                other.to_string(), at.line.to_string(), at.col.to_string(),
+            ]));
+        }
+        seen.push((skel, name, tok));
+    }
+    warnings
 }
\ No newline at end of file
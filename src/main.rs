@@ -4,6 +4,9 @@ mod executor;
 mod lexer;
 mod parser;
 mod ast;
+mod unicode;
+mod messages;
+mod doc;
 
 use version::show_version;
 use cli::{parse_args, CliAction, show_help};
@@ -11,20 +14,40 @@ use executor::{execute_file, dump_tokens, dump_ast, syntax_check};
 use std::env;
 
 fn main() {
+    // 目录里的诊断已带 error[编号] 前缀，直接打印（被 try 接住时不打印）；其他 panic 视为内部错误
     std::panic::set_hook(Box::new(|info| {
-        eprintln!("error: {}", info);
+        let payload = info.payload();
+        let msg = payload.downcast_ref::<String>().map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied());
+        match msg {
+            Some(m) if m.starts_with("error[") => {
+                if !messages::is_catching() {
+                    eprintln!("{}", m);
+                }
+            }
+            _ => eprintln!("error: {}", info),
+        }
     }));
     let args: Vec<String> = env::args().collect();
 
-    match parse_args(&args) {
+    let action = parse_args(&args);
+    if let CliAction::RunFile(_, options)
+        | CliAction::ShowTokens(_, options)
+        | CliAction::ShowAst(_, options)
+        | CliAction::SyntaxCheck(_, options)
+        | CliAction::ShowDocs(_, _, options) = &action {
+        messages::set_lang(options.lang.as_deref());
+    }
+    match action {
         CliAction::ShowHelp => show_help(),
         CliAction::ShowVersion => show_version(),
-        CliAction::RunFile(path) => execute_file(&path),
-        CliAction::ShowTokens(path) => dump_tokens(&path),
-        CliAction::ShowAst(path) => dump_ast(&path),
-        CliAction::SyntaxCheck(path) => syntax_check(&path),
+        CliAction::RunFile(path, options) => execute_file(&path, &options),
+        CliAction::ShowTokens(path, options) => dump_tokens(&path, &options),
+        CliAction::ShowAst(path, options) => dump_ast(&path, &options),
+        CliAction::SyntaxCheck(path, options) => syntax_check(&path, &options),
+        CliAction::ShowDocs(path, format, options) => doc::render_docs(&path, format, &options),
         CliAction::Invalid(arg) => {
-            eprintln!("Unknown argument: {}", arg);
+            eprintln!("{}", messages::error("E002", None, &[arg]));
             show_help();
         }
     }
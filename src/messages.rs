@@ -0,0 +1,327 @@
+// 诊断信息目录：每条错误与警告都有稳定编号（L 词法、P 语法、R 运行时、W 警告、E 命令行），
+// 正文按 --lang 或环境变量 FLYUX_LANG 在英文与中文之间切换。
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Lang {
+    En,
+    Zh,
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+fn pick(code: Option<&str>) -> Lang {
+    let env = std::env::var("FLYUX_LANG").ok();
+    match code.or(env.as_deref()) {
+        Some(c) if c.starts_with("zh") => Lang::Zh,
+        _ => Lang::En,
+    }
+}
+
+// --lang 优先于 FLYUX_LANG；只在启动时设置一次
+pub fn set_lang(code: Option<&str>) {
+    let _ = LANG.set(pick(code));
+}
+
+fn lang() -> Lang {
+    *LANG.get_or_init(|| pick(None))
+}
+
+// (编号, 英文, 中文)；{0}、{1} 为按位置的参数，{{ 与 }} 表示字面花括号
+const CATALOG: &[(&str, &str, &str)] = &[
+    ("P001", "Expected function name", "缺少函数名"),
+    ("P002", "Expected method name after '{0}.'", "'{0}.' 后缺少方法名"),
+    ("P003", "Expected parameter name", "缺少参数名"),
+    ("P004", "Expected type after (", "( 后缺少类型"),
+    ("P005", "Expected ) after type", "类型后缺少 )"),
+    ("P006", "Expected interface name after ':'", "':' 后缺少接口名"),
+    ("P007", "Expected interface name after P>", "P> 后缺少接口名"),
+    ("P008", "Expected '{{' after interface name", "接口名后缺少 '{{'"),
+    ("P009", "Expected method name in interface", "接口中缺少方法名"),
+    ("P010", "Expected parameter name in '{0}'", "'{0}' 中缺少参数名"),
+    ("P011", "Expected enum name after E>", "E> 后缺少枚举名"),
+    ("P012", "Expected '{{' after enum name", "枚举名后缺少 '{{'"),
+    ("P013", "Expected variant name", "缺少变体名"),
+    ("P014", "Duplicate variant '{0}' in enum '{1}'", "枚举 '{1}' 中的变体 '{0}' 重复"),
+    ("P015", "Expected payload name in variant '{0}'", "变体 '{0}' 中缺少载荷名"),
+    ("P016", "Expected type name after T>", "T> 后缺少类型名"),
+    ("P017", "Expected '{{' after type name", "类型名后缺少 '{{'"),
+    ("P018", "Expected field name", "缺少字段名"),
+    ("P019", "Duplicate field '{0}' in type '{1}'", "类型 '{1}' 中的字段 '{0}' 重复"),
+    ("P020", "Expected ) after field type", "字段类型后缺少 )"),
+    ("P021", "Expected ')' after if condition", "if 条件后缺少 ')'"),
+    ("P022", "Expected '{{' after if condition", "if 条件后缺少 '{{'"),
+    ("P023", "Expected '(' after if", "if 后缺少 '('"),
+    ("P024", "Expected '(' after elif", "elif 后缺少 '('"),
+    ("P025", "Expected ')' after elif condition", "elif 条件后缺少 ')'"),
+    ("P026", "Expected '{{' after elif condition", "elif 条件后缺少 '{{'"),
+    ("P027", "Expected '{{' after else", "else 后缺少 '{{'"),
+    ("P028", "Expected ')' after condition", "条件后缺少 ')'"),
+    ("P029", "Expected '{{' after condition", "条件后缺少 '{{'"),
+    ("P030", "Expected type inside ()", "() 中缺少类型"),
+    ("P031", "Expected )", "缺少 )"),
+    ("P032", "Expected type inside []", "[] 中缺少类型"),
+    ("P033", "Expected ]", "缺少 ]"),
+    ("P034", "Expected type after :(", ":( 后缺少类型"),
+    ("P035", "Expected ) after constant type", "常量类型后缺少 )"),
+    ("P036", "Expected = after constant type", "常量类型后缺少 ="),
+    ("P037", "Expected type after :[", ":[ 后缺少类型"),
+    ("P038", "Expected ] after variable type", "变量类型后缺少 ]"),
+    ("P039", "Expected = after variable type", "变量类型后缺少 ="),
+    ("P040", "Expected :() or :[] for type declaration", "类型声明应为 :() 或 :[]"),
+    ("P041", "Expected variable name after <=>", "<=> 后缺少变量名"),
+    ("P042", "Expected := after variable name", "变量名后缺少 :="),
+    ("P043", "Unknown statement", "无法识别的语句"),
+    ("P044", "Expected binding name in pattern, got {0}", "模式中应为绑定名，实际为 {0}"),
+    ("P045", "Expected Enum.Variant after 'is'", "'is' 后应为 枚举.变体"),
+    ("P046", "Expected ')' to close grouping", "缺少与 '(' 配对的 ')'"),
+    ("P047", "Expected key in object literal", "对象字面量中缺少键"),
+    ("P048", "Expected ':' after object key", "对象键后缺少 ':'"),
+    ("P049", "Expected field name in '{0}{{...}}'", "'{0}{{...}}' 中缺少字段名"),
+    ("P050", "Expected ':' after field name", "字段名后缺少 ':'"),
+    ("P051", "Unsupported expression token {0}", "表达式中不支持的 Token：{0}"),
+    ("P052", "Unexpected end of input while parsing expression", "解析表达式时输入意外结束"),
+    ("P053", "Expected method name after '|'", "'|' 后缺少方法名"),
+    ("P054", "Expected ')' after method args", "方法参数后缺少 ')'"),
+    ("P055", "Expected method name after ?.>", "?.> 后缺少方法名"),
+    ("P056", "Expected property name after '?.'", "'?.' 后缺少属性名"),
+    ("P057", "Expected method name after .>", ".> 后缺少方法名"),
+    ("P058", "Expected ')' after arguments", "参数后缺少 ')'"),
+    ("P059", "Expected property name after '.'", "'.' 后缺少属性名"),
+    ("P060", "Expected ']' after slice", "切片后缺少 ']'"),
+    ("P061", "Expected ']' after index", "下标后缺少 ']'"),
+    ("P062", "Expected ':' after iterable expression", "可迭代表达式后缺少 ':'"),
+    ("P063", "Expected variable name or (index, item) after ':'", "':' 后应为变量名或 (下标, 元素)"),
+    ("P064", "Expected '}}' to close loop body", "循环体缺少结尾的 '}}'"),
+    ("P065", "Expected '(' before loop condition", "循环条件前缺少 '('"),
+    ("P066", "Expected ')' after loop condition", "循环条件后缺少 ')'"),
+    ("P067", "Expected L>", "此处应为 L>"),
+    ("P068", "Expected ']' or '{{' after loop expression", "循环表达式后缺少 ']' 或 '{{'"),
+    ("P069", "Expected counter name after ':'", "':' 后缺少计数变量名"),
+    ("P070", "Expected ';', ')', or '{{' in loop header", "循环头中应为 ';'、')' 或 '{{'"),
+    ("P071", "Invalid while-loop header: expected 1 condition expression", "while 循环头无效：应只有一个条件表达式"),
+    ("P072", "Expected ';' after init in for-loop header", "for 循环头的初始化后缺少 ';'"),
+    ("P073", "Expected ';' after condition in for-loop header", "for 循环头的条件后缺少 ';'"),
+    ("P074", "Unknown loop format", "无法识别的循环格式"),
+    ("P075", "Expected '{{' after loop header", "循环头后缺少 '{{'"),
+    ("P076", "Expected '(cond)' or '!(cond)' after ':' in a do-while loop", "do-while 循环的 ':' 后应为 '(条件)' 或 '!(条件)'"),
+    ("R001", "Cannot redefine method '{0}.{1}'", "方法 '{0}.{1}' 重复定义"),
+    ("R002", "Cannot redefine type '{0}'", "类型 '{0}' 重复定义"),
+    ("R003", "Cannot redefine interface '{0}'", "接口 '{0}' 重复定义"),
+    ("R004", "Unknown interface '{0}' claimed by '{1}'", "'{1}' 声明的接口 '{0}' 不存在"),
+    ("R005", "Type '{0}' does not conform to '{1}': method '{2}' takes {3} argument(s), expected {4}", "类型 '{0}' 不符合接口 '{1}'：方法 '{2}' 接受 {3} 个参数，应为 {4} 个"),
+    ("R006", "Type '{0}' does not conform to '{1}': missing method '{2}'", "类型 '{0}' 不符合接口 '{1}'：缺少方法 '{2}'"),
+    ("R007", "Unknown type '{0}' for method '{1}'", "方法 '{1}' 所属的类型 '{0}' 不存在"),
+    ("R008", "Unknown type '{0}' for field '{1}.{2}'", "字段 '{1}.{2}' 的类型 '{0}' 不存在"),
+    ("R009", "Not a channel: '{0}'", "不是通道：'{0}'"),
+    ("R010", "Iterator <iter#{0}> was already consumed", "迭代器 <iter#{0}> 已被消费"),
+    ("R011", "Type '{0}' has no field '{1}'", "类型 '{0}' 没有字段 '{1}'"),
+    ("R012", "Key '{0}' not found during nested assignment", "嵌套赋值时找不到键 '{0}'"),
+    ("R013", "Not an enum value: '{0}'", "不是枚举值：'{0}'"),
+    ("R014", "{0} needs a function argument", "{0} 需要一个函数参数"),
+    ("R015", "Unknown function '{0}'", "未知函数 '{0}'"),
+    ("R016", "take needs a non-negative integer", "take 需要一个非负整数"),
+    ("R017", "{0} needs one more argument", "{0} 需要一个额外参数"),
+    ("R018", "{0} needs a number of milliseconds", "{0} 需要毫秒数"),
+    ("R019", "Not a thread: '{0}'", "不是线程：'{0}'"),
+    ("R020", "Thread {0} was already joined", "线程 {0} 已经 join 过"),
+    ("R021", "cancel needs a timer", "cancel 需要一个定时器"),
+    ("R022", "Not an object: '{0}'", "不是对象：'{0}'"),
+    ("R023", "Not an array: '{0}'", "不是数组：'{0}'"),
+    ("R024", "Range step cannot be 0", "区间步长不能为 0"),
+    ("R025", "Enum '{0}' has no variant '{1}'", "枚举 '{0}' 没有变体 '{1}'"),
+    ("R026", "Variant '{0}.{1}' expects {2} payload value(s), got {3}", "变体 '{0}.{1}' 需要 {2} 个载荷值，实际为 {3} 个"),
+    ("R027", "Type mismatch for field '{0}.{1}': expected {2}, got '{3}'", "字段 '{0}.{1}' 类型不匹配：应为 {2}，实际为 '{3}'"),
+    ("R028", "Cannot redefine constant '{0}'", "常量 '{0}' 不能重复定义"),
+    ("R029", "Cyclic reactive binding: {0}", "响应式绑定存在循环：{0}"),
+    ("R030", "Cannot assign to reactive binding '{0}'", "不能给响应式绑定 '{0}' 赋值"),
+    ("R031", "Type '{0}' does not support operator '{1}' (define '{2}')", "类型 '{0}' 不支持运算符 '{1}'（请定义 '{2}'）"),
+    ("R032", "'length' of {0} returned a non-count: '{1}'", "{0} 的 'length' 返回的不是计数：'{1}'"),
+    ("R033", "Value is not iterable: {0}", "值不可迭代：{0}"),
+    ("R034", "{0}() can only be used inside a coroutine", "{0}() 只能在协程中使用"),
+    ("R035", "For-each target is not iterable: {0}", "for-each 的目标不可迭代：{0}"),
+    ("R036", "send on a closed channel", "向已关闭的通道发送"),
+    ("R037", "B> used outside of a loop in function '{0}'", "函数 '{0}' 中的 B> 不在循环内"),
+    ("R038", "stack overflow: call depth exceeded {0} in '{1}' (raise it with --max-depth)", "栈溢出：'{1}' 中的调用深度超过 {0}（可用 --max-depth 调高）"),
+    ("R039", "Unknown type '{0}'", "未知类型 '{0}'"),
+    ("R040", "Type mismatch: expected {0}, got '{1}'", "类型不匹配：应为 {0}，实际为 '{1}'"),
+    ("R041", "Invalid boolean literal: '{0}'", "无效的布尔字面量：'{0}'"),
+    ("R042", "Invalid int literal: '{0}'", "无效的整数字面量：'{0}'"),
+    ("R043", "Invalid float literal: '{0}'", "无效的浮点数字面量：'{0}'"),
+    ("R044", "Undefined variable '{0}'", "未定义的变量 '{0}'"),
+    ("R045", "Cannot assign to constant '{0}'", "不能给常量 '{0}' 赋值"),
+    ("R046", "Unsupported type '{0}'", "不支持的类型 '{0}'"),
+    ("R047", "Invalid loop count: {0}", "无效的循环次数：{0}"),
+    ("R048", "B> and R> cannot be used inside a parallel loop", "并行循环中不能使用 B> 和 R>"),
+    ("R049", "Variable '{0}' not found for increment", "自增的变量 '{0}' 不存在"),
+    ("R050", "Invalid int for increment: '{0}'", "自增时整数无效：'{0}'"),
+    ("R051", "Invalid float for increment: '{0}'", "自增时浮点数无效：'{0}'"),
+    ("R052", "Unsupported type '{0}' for increment", "自增不支持类型 '{0}'"),
+    ("R053", "Variable '{0}' not found for decrement", "自减的变量 '{0}' 不存在"),
+    ("R054", "Invalid int for decrement: '{0}'", "自减时整数无效：'{0}'"),
+    ("R055", "Invalid float for decrement: '{0}'", "自减时浮点数无效：'{0}'"),
+    ("R056", "Unsupported type '{0}' for decrement", "自减不支持类型 '{0}'"),
+    ("R057", "'{0}' is not found for nested assignment", "嵌套赋值的目标 '{0}' 不存在"),
+    ("R058", "Invalid nested left-hand side: {0}", "无效的嵌套赋值左侧：{0}"),
+    ("R059", "Invalid index for '{0}'", "'{0}' 的下标无效"),
+    ("R060", "Index {0} out of bounds for {1}", "下标 {0} 超出 {1} 的范围"),
+    ("R061", "'{0}' is not defined", "'{0}' 未定义"),
+    ("R062", "'{0}' is not found for nested array assignment", "嵌套数组赋值的目标 '{0}' 不存在"),
+    ("R063", "Invalid nested array target: {0}", "无效的嵌套数组目标：{0}"),
+    ("R064", "Invalid left-hand side in property assignment: {0}", "属性赋值的左侧无效：{0}"),
+    ("R065", "Unknown method '{0}'", "未知方法 '{0}'"),
+    ("R066", "Undefined identifier in postfix ++: '{0}'", "后缀 ++ 的标识符未定义：'{0}'"),
+    ("R067", "Invalid int in postfix ++: '{0}'", "后缀 ++ 的整数无效：'{0}'"),
+    ("R068", "Invalid float in postfix ++: '{0}'", "后缀 ++ 的浮点数无效：'{0}'"),
+    ("R069", "Unsupported type '{0}' for postfix ++ on '{1}'", "'{1}' 的后缀 ++ 不支持类型 '{0}'"),
+    ("R070", "Undefined identifier in postfix --: '{0}'", "后缀 -- 的标识符未定义：'{0}'"),
+    ("R071", "Invalid int in postfix --: '{0}'", "后缀 -- 的整数无效：'{0}'"),
+    ("R072", "Invalid float in postfix --: '{0}'", "后缀 -- 的浮点数无效：'{0}'"),
+    ("R073", "Unsupported type '{0}' for postfix -- on '{1}'", "'{1}' 的后缀 -- 不支持类型 '{0}'"),
+    ("R074", "Undefined identifier: '{0}'", "未定义的标识符：'{0}'"),
+    ("R075", "Unsupported logical operator: {0}", "不支持的逻辑运算符：{0}"),
+    ("R076", "Cannot index null with [{0}]", "不能对 null 取下标 [{0}]"),
+    ("R077", "Invalid index: '{0}'", "无效的下标：'{0}'"),
+    ("R078", "Cannot slice null", "不能对 null 切片"),
+    ("R079", "Invalid slice bound: '{0}'", "无效的切片边界：'{0}'"),
+    ("R080", "Cannot read property '{0}' of null (use ?. for optional access)", "不能读取 null 的属性 '{0}'（可用 ?. 做可选访问）"),
+    ("R081", "Variant '{0}.{1}' has no field '{2}'", "变体 '{0}.{1}' 没有字段 '{2}'"),
+    ("R082", "Not an object: {0}", "不是对象：{0}"),
+    ("R083", "Unknown enum '{0}'", "未知枚举 '{0}'"),
+    ("R084", "Pattern '{0}.{1}' binds {2} value(s), variant has {3}", "模式 '{0}.{1}' 绑定了 {2} 个值，变体有 {3} 个"),
+    ("R085", "Range {0} must be an integer, got '{1}'", "区间的 {0} 必须是整数，实际为 '{1}'"),
+    ("R086", "Field '{0}' given twice in '{1}' construction", "构造 '{1}' 时字段 '{0}' 给了两次"),
+    ("R087", "Missing field '{0}' in '{1}' construction", "构造 '{1}' 时缺少字段 '{0}'"),
+    ("R088", "Cannot convert '{0}' to {1}", "无法把 '{0}' 转换为 {1}"),
+    ("R089", "'{0}' is not a number", "'{0}' 不是数字"),
+    ("R090", "Unknown operator '{0}'", "未知运算符 '{0}'"),
+    ("R091", "Division by zero", "除以零"),
+    ("R092", "Range index {0} out of bounds (length {1})", "区间下标 {0} 越界（长度 {1}）"),
+    ("R093", "Key '{0}' not found in object", "对象中没有键 '{0}'"),
+    ("R094", "Array index {0} out of bounds (length {1})", "数组下标 {0} 越界（长度 {1}）"),
+    ("R095", "String index {0} out of bounds (length {1})", "字符串下标 {0} 越界（长度 {1}）"),
+    ("R096", "Cannot slice '{0}'", "不能切片 '{0}'"),
+    ("R097", "Property '{0}' not found in object", "对象中没有属性 '{0}'"),
+    ("R098", "'{0}' is a literal and cannot be declared as a variable", "'{0}' 是字面量，不能声明为变量"),
+    ("R099", "Range {0} has too many elements", "区间 {0} 的元素过多"),
+    ("R100", "stack overflow: calls nested through operators, callbacks or bindings in '{0}' are too deep", "栈溢出：'{0}' 中经由运算符、回调或绑定嵌套的调用过深"),
+    ("R101", "Y> cannot be used inside a parallel loop", "Y> 不能用在并行循环里"),
+    ("R102", "deadlock: task '{0}' waits for a task that can never finish", "死锁：任务 '{0}' 等待的任务永远不会结束"),
+    ("R103", "{0} needs an argument", "{0} 需要一个参数"),
+    ("R104", "'{0}' takes {1} argument(s), got {2}", "'{0}' 接受 {1} 个参数，实际传入 {2} 个"),
+    ("L001", "Unknown language '{0}'", "未知语言 '{0}'"),
+    ("L002", "Zero-width joiner outside an emoji sequence in identifier '{0}'", "标识符 '{0}' 中的零宽连接符不在 emoji 序列内"),
+    ("L003", "Zero-width non-joiner in identifier '{0}'", "标识符 '{0}' 中含有零宽非连接符"),
+    ("L004", "Malformed number literal '{0}'", "数字字面量格式错误：'{0}'"),
+    ("L005", "Invalid digit '{0}' in base-{1} literal '{2}'", "{1} 进制字面量 '{2}' 中的数字 '{0}' 无效"),
+    ("L006", "Integer literal '{0}' is out of range", "整数字面量 '{0}' 超出范围"),
+    ("L007", "Unterminated string literal", "字符串字面量缺少结束引号"),
+    ("L008", "Invalid escape '{0}' in string literal", "字符串字面量中的转义 '{0}' 无效"),
+    ("L009", "Unterminated block comment", "块注释缺少结尾的 */"),
+    ("L010", "Unexpected character '{0}'", "无法识别的字符 '{0}'"),
+    ("L011", "Reserved character U+FDD0", "保留字符 U+FDD0"),
+    ("L012", "Float literal '{0}' is out of range", "浮点数字面量 '{0}' 超出范围"),
+    ("W001", "'=::' overrides constant '{0}'", "'=::' 覆盖了常量 '{0}'"),
+    ("W002", "Identifier '{0}' (line {1}, col {2}) looks like '{3}' (line {4}, col {5})", "标识符 '{0}'（第 {1} 行，第 {2} 列）与 '{3}'（第 {4} 行，第 {5} 列）外形相同"),
+    ("E001", "Failed to read file: {0}", "无法读取文件：{0}"),
+    ("E002", "Unknown argument: {0}", "未知参数：{0}"),
+];
+
+fn render(template: &str, args: &[String]) -> String {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut n = String::new();
+                for d in chars.by_ref() {
+                    if d == '}' {
+                        break;
+                    }
+                    n.push(d);
+                }
+                let i: usize = n.parse().expect("bad placeholder in message catalog");
+                // 参数里的运行时值按 print 的方式显示（去掉内部值标记）
+                out.extend(args[i].chars().filter(|&c| c != crate::lexer::VALUE_TAG));
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn text(code: &str, args: &[String]) -> String {
+    let (_, en, zh) = CATALOG
+        .iter()
+        .find(|(c, _, _)| *c == code)
+        .unwrap_or_else(|| panic!("unknown message code {}", code));
+    render(if lang() == Lang::Zh { zh } else { en }, args)
+}
+
+// 错误的完整形式：error[P012]: Parse error at line 3, col 5: ...
+pub fn error(code: &str, at: Option<(usize, usize)>, args: &[String]) -> String {
+    let zh = lang() == Lang::Zh;
+    let prefix = match (code.as_bytes()[0], at, zh) {
+        (b'L', Some((l, c)), false) => format!("Lex error at line {}, col {}: ", l, c),
+        (b'L', Some((l, c)), true) => format!("词法错误（第 {} 行，第 {} 列）：", l, c),
+        (b'P', Some((l, c)), false) => format!("Parse error at line {}, col {}: ", l, c),
+        (b'P', Some((l, c)), true) => format!("语法错误（第 {} 行，第 {} 列）：", l, c),
+        (b'P', None, false) => "Parse error: ".to_string(),
+        (b'P', None, true) => "语法错误：".to_string(),
+        (_, Some((l, c)), false) => format!("Runtime error at line {}, col {}: ", l, c),
+        (_, Some((l, c)), true) => format!("运行时错误（第 {} 行，第 {} 列）：", l, c),
+        _ => String::new(),
+    };
+    format!("error[{}]: {}{}", code, prefix, text(code, args))
+}
+
+thread_local! {
+    // 当前线程上仍在执行的 try(...) 层数
+    static CATCHING: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+// f 中的目录错误交给调用者，得到完整的错误信息；其他 panic（内部错误）照常向外传播
+pub fn caught<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    CATCHING.set(CATCHING.get() + 1);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    CATCHING.set(CATCHING.get() - 1);
+    result.map_err(|payload| match payload.downcast::<String>() {
+        Ok(m) if m.starts_with("error[") => *m,
+        Ok(m) => std::panic::resume_unwind(m),
+        Err(payload) => std::panic::resume_unwind(payload),
+    })
+}
+
+// try(f, ...)：同上，但只要错误编号
+pub fn catching<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    caught(f).map_err(|m| m["error[".len()..].split_once(']').map_or(m.clone(), |(code, _)| code.to_string()))
+}
+
+// 被 try 接住的错误不打印
+pub fn is_catching() -> bool {
+    CATCHING.get() > 0
+}
+
+pub fn warning(code: &str, args: &[String]) -> String {
+    format!("warning[{}]: {}", code, text(code, args))
+}
+
+// fail!(at token, "P001", args...) 带 token 的行列；fail!("R001", args...) 不带位置
+macro_rules! fail {
+    (at $tok:expr, $code:literal $(, $arg:expr)* $(,)?) => {{
+        let tok = &$tok;
+        panic!("{}", $crate::messages::error($code, Some((tok.line, tok.col)), &[$($arg.to_string()),*]))
+    }};
+    ($code:literal $(, $arg:expr)* $(,)?) => {
+        panic!("{}", $crate::messages::error($code, None, &[$($arg.to_string()),*]))
+    };
+}
+pub(crate) use fail;
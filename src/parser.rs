@@ -1,35 +1,91 @@
-use crate::lexer::{Token, TokenKind};
-use crate::ast::{Expr, Stmt, Function};
+use crate::lexer::{Token, TokenKind, NumLit};
+use crate::ast::{Expr, Stmt, Function, TypeDecl, EnumDecl, InterfaceDecl, Program};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::messages::fail;
 
-pub fn parse(tokens: &[Token]) -> Vec<Function> {
+// 调用点与运算点的唯一编号：下标即编号，值为源码位置 (line, col)
+static SITES: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+fn next_site(at: &Token) -> usize {
+    let mut sites = SITES.lock().unwrap();
+    sites.push((at.line, at.col));
+    sites.len() - 1
+}
+
+// Source position of a site, for runtime errors.
+pub fn site_position(site: usize) -> (usize, usize) {
+    SITES.lock().unwrap().get(site).copied().unwrap_or((0, 0))
+}
+
+// 注释不参与语法；提前去掉，块末尾的行尾注释也就不会被当成语句。
+// `///` 文档注释记到紧随其后的 F> 上（键为过滤后的下标），中间隔了代码就作废
+fn strip_comments(tokens: &[Token]) -> (Vec<Token>, HashMap<usize, String>) {
+    let mut docs: HashMap<usize, String> = HashMap::new();
+    let mut pending: Vec<&str> = Vec::new();
+    let mut filtered: Vec<Token> = Vec::new();
+    for t in tokens {
+        match &t.kind {
+            TokenKind::DocComment(text) => pending.push(text),
+            TokenKind::Comment(_) | TokenKind::Whitespace => {}
+            TokenKind::Unknown(c) => fail!(at t, "L010", c),
+            kind => {
+                if matches!(kind, TokenKind::Fn) && !pending.is_empty() {
+                    docs.insert(filtered.len(), pending.join("\n"));
+                }
+                pending.clear();
+                filtered.push(t.clone());
+            }
+        }
+    }
+    (filtered, docs)
+}
+
+pub fn parse(tokens: &[Token]) -> Program {
+    let (filtered, mut docs) = strip_comments(tokens);
+    let tokens = filtered.as_slice();
     let mut index = 0;
     let mut fns = Vec::new();
+    let mut types = Vec::new();
+    let mut enums = Vec::new();
+    let mut interfaces = Vec::new();
 
     while index < tokens.len() {
-        if matches!(tokens.get(index), Some(Token { kind: TokenKind::Fn, .. })) {
+        if matches!(tokens.get(index), Some(Token { kind: TokenKind::Type, .. })) {
+            types.push(parse_type_decl(tokens, &mut index));
+        } else if matches!(tokens.get(index), Some(Token { kind: TokenKind::Enum, .. })) {
+            enums.push(parse_enum_decl(tokens, &mut index));
+        } else if matches!(tokens.get(index), Some(Token { kind: TokenKind::Interface, .. })) {
+            interfaces.push(parse_interface_decl(tokens, &mut index));
+        } else if matches!(tokens.get(index), Some(Token { kind: TokenKind::Fn, .. })) {
+            let doc = docs.remove(&index);
             index += 1;
 
-            let name = match tokens.get(index) {
+            let mut name = match tokens.get(index) {
                 Some(Token { kind: TokenKind::Ident(id), .. }) => id.clone(),
-                _ => panic!(
-                    "Parse error at line {}, col {}: Expected function name",
-                    tokens[index].line,
-                    tokens[index].col
-                ),
+                _ => fail!(at tokens[index], "P001"),
             };
             index += 1;
 
+            // F>Type.method(...)
+            let mut owner = None;
+            if matches!(tokens.get(index), Some(Token { kind: TokenKind::Dot, .. })) {
+                index += 1;
+                let method = match tokens.get(index) {
+                    Some(Token { kind: TokenKind::Ident(id), .. }) => id.clone(),
+                    _ => fail!(at tokens[index], "P002", name),
+                };
+                index += 1;
+                owner = Some(std::mem::replace(&mut name, method));
+            }
+
             let mut params = Vec::new();
             if matches!(tokens.get(index), Some(Token { kind: TokenKind::LParen, .. })) {
                 index += 1;
                 while !matches!(tokens.get(index), Some(Token { kind: TokenKind::RParen, .. })) {
                     let param_name = match tokens.get(index) {
                         Some(Token { kind: TokenKind::Ident(p), .. }) => p.clone(),
-                        _ => panic!(
-                            "Parse error at line {}, col {}: Expected parameter name",
-                            tokens[index].line,
-                            tokens[index].col
-                        ),
+                        _ => fail!(at tokens[index], "P003"),
                     };
                     index += 1;
 
@@ -38,19 +94,11 @@ pub fn parse(tokens: &[Token]) -> Vec<Function> {
                         index += 1;
                         param_type = match tokens.get(index) {
                             Some(Token { kind: TokenKind::Ident(t), .. }) => Some(t.clone()),
-                            _ => panic!(
-                                "Parse error at line {}, col {}: Expected type after (",
-                                tokens[index].line,
-                                tokens[index].col
-                            ),
+                            _ => fail!(at tokens[index], "P004"),
                         };
                         index += 1;
                         if !matches!(tokens.get(index), Some(Token { kind: TokenKind::RParen, .. })) {
-                            panic!(
-                                "Parse error at line {}, col {}: Expected ) after type",
-                                tokens[index].line,
-                                tokens[index].col
-                            );
+                            fail!(at tokens[index], "P005");
                         }
                         index += 1;
                     }
@@ -74,32 +122,212 @@ pub fn parse(tokens: &[Token]) -> Vec<Function> {
             }
             index += 1;
 
-            fns.push(Function { name, params, body });
+            fns.push(Function { name, owner, params, body, doc });
         } else {
             index += 1;
         }
     }
 
-    fns
+    Program { types, enums, interfaces, functions: fns }
 }
 
-fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
-    // 跳过注释和空白 token
+// Optional conformance list after a type name: `: Iterable, Sized`
+fn parse_conformance(tokens: &[Token], index: &mut usize) -> Vec<String> {
+    let mut interfaces = Vec::new();
+    if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. })) {
+        return interfaces;
+    }
+    *index += 1;
     loop {
         match tokens.get(*index) {
-            Some(Token { kind: TokenKind::Comment(_), .. }) |
-            Some(Token { kind: TokenKind::Whitespace, .. }) => {
+            Some(Token { kind: TokenKind::Ident(i), .. }) => interfaces.push(i.clone()),
+            _ => fail!(at tokens[*index], "P006"),
+        }
+        *index += 1;
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Comma, .. })) {
+            *index += 1;
+        } else {
+            break;
+        }
+    }
+    interfaces
+}
+
+// P>Name{ method, method(a, b), ... }
+fn parse_interface_decl(tokens: &[Token], index: &mut usize) -> InterfaceDecl {
+    *index += 1; // skip P>
+
+    let name = match tokens.get(*index) {
+        Some(Token { kind: TokenKind::Ident(id), .. }) => id.clone(),
+        _ => fail!(at tokens[*index], "P007"),
+    };
+    *index += 1;
+
+    if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. })) {
+        fail!(at tokens[*index], "P008");
+    }
+    *index += 1;
+
+    let mut methods: Vec<(String, usize)> = Vec::new();
+    while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBrace, .. })) {
+        let method = match tokens.get(*index) {
+            Some(Token { kind: TokenKind::Ident(m), .. }) => m.clone(),
+            Some(Token { kind: TokenKind::Comment(_), .. }) => {
                 *index += 1;
+                continue;
             }
-            _ => break,
+            _ => fail!(at tokens[*index], "P009"),
+        };
+        *index += 1;
+
+        let mut arity = 0;
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::LParen, .. })) {
+            *index += 1;
+            while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
+                if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Ident(_), .. })) {
+                    fail!(at tokens[*index], "P010", method);
+                }
+                arity += 1;
+                *index += 1;
+                if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Comma, .. })) {
+                    *index += 1;
+                }
+            }
+            *index += 1; // skip RParen
+        }
+
+        methods.push((method, arity));
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Comma, .. })) {
+            *index += 1;
         }
     }
+    *index += 1; // skip RBrace
+
+    InterfaceDecl { name, methods }
+}
+
+// E>Name{ Variant(field, ...), Unit, ... }
+fn parse_enum_decl(tokens: &[Token], index: &mut usize) -> EnumDecl {
+    *index += 1; // skip E>
+
+    let name = match tokens.get(*index) {
+        Some(Token { kind: TokenKind::Ident(id), .. }) => id.clone(),
+        _ => fail!(at tokens[*index], "P011"),
+    };
+    *index += 1;
+    let interfaces = parse_conformance(tokens, index);
+
+    if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. })) {
+        fail!(at tokens[*index], "P012");
+    }
+    *index += 1;
+
+    let mut variants: Vec<(String, Vec<String>)> = Vec::new();
+    while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBrace, .. })) {
+        let variant = match tokens.get(*index) {
+            Some(Token { kind: TokenKind::Ident(v), .. }) => v.clone(),
+            Some(Token { kind: TokenKind::Comment(_), .. }) => {
+                *index += 1;
+                continue;
+            }
+            _ => fail!(at tokens[*index], "P013"),
+        };
+        if variants.iter().any(|(v, _)| *v == variant) {
+            fail!(at tokens[*index], "P014", variant, name);
+        }
+        *index += 1;
+
+        let mut fields = Vec::new();
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::LParen, .. })) {
+            *index += 1;
+            while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
+                match tokens.get(*index) {
+                    Some(Token { kind: TokenKind::Ident(f), .. }) => fields.push(f.clone()),
+                    _ => fail!(at tokens[*index], "P015", variant),
+                }
+                *index += 1;
+                if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Comma, .. })) {
+                    *index += 1;
+                }
+            }
+            *index += 1; // skip RParen
+        }
+
+        variants.push((variant, fields));
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Comma, .. })) {
+            *index += 1;
+        }
+    }
+    *index += 1; // skip RBrace
+
+    EnumDecl { name, interfaces, variants }
+}
+
+// T>Name{ field(type), field2, ... }
+fn parse_type_decl(tokens: &[Token], index: &mut usize) -> TypeDecl {
+    *index += 1; // skip T>
+
+    let name = match tokens.get(*index) {
+        Some(Token { kind: TokenKind::Ident(id), .. }) => id.clone(),
+        _ => fail!(at tokens[*index], "P016"),
+    };
+    *index += 1;
+    let interfaces = parse_conformance(tokens, index);
+
+    if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. })) {
+        fail!(at tokens[*index], "P017");
+    }
+    *index += 1;
+
+    let mut fields: Vec<(String, Option<String>)> = Vec::new();
+    while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBrace, .. })) {
+        let field_name = match tokens.get(*index) {
+            Some(Token { kind: TokenKind::Ident(f), .. }) => f.clone(),
+            Some(Token { kind: TokenKind::Comment(_), .. }) => {
+                *index += 1;
+                continue;
+            }
+            _ => fail!(at tokens[*index], "P018"),
+        };
+        if fields.iter().any(|(f, _)| *f == field_name) {
+            fail!(at tokens[*index], "P019", field_name, name);
+        }
+        *index += 1;
+
+        let mut field_type = None;
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::LParen, .. })) {
+            *index += 1;
+            field_type = match tokens.get(*index) {
+                Some(Token { kind: TokenKind::Ident(t), .. }) => Some(t.clone()),
+                _ => fail!(at tokens[*index], "P004"),
+            };
+            *index += 1;
+            if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
+                fail!(at tokens[*index], "P020");
+            }
+            *index += 1;
+        }
+
+        fields.push((field_name, field_type));
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Comma, .. })) {
+            *index += 1;
+        }
+    }
+    *index += 1; // skip RBrace
+
+    TypeDecl { name, interfaces, fields }
+}
+
+fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
+    // 跳过注释和空白 token
+    while let Some(Token { kind: TokenKind::Comment(_) | TokenKind::Whitespace, .. }) = tokens.get(*index) {
+        *index += 1;
+    }
     // Prefix increment/decrement: ++a or --a
-    if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Unknown('+'), .. }))
-        && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('+'), .. }))
-        && matches!(tokens.get(*index + 2), Some(Token { kind: TokenKind::Ident(_), .. })) {
+    if matches!(tokens.get(*index), Some(Token { kind: TokenKind::PlusPlus, .. }))
+        && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Ident(_), .. })) {
         // consume '++'
-        *index += 2;
+        *index += 1;
         // capture variable name
         if let Token { kind: TokenKind::Ident(name), .. } = tokens.get(*index).unwrap() {
             let var = name.clone();
@@ -107,10 +335,9 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
             return Stmt::Increment(var);
         }
     }
-    if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Unknown('-'), .. }))
-        && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('-'), .. }))
-        && matches!(tokens.get(*index + 2), Some(Token { kind: TokenKind::Ident(_), .. })) {
-        *index += 2;
+    if matches!(tokens.get(*index), Some(Token { kind: TokenKind::MinusMinus, .. }))
+        && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Ident(_), .. })) {
+        *index += 1;
         if let Token { kind: TokenKind::Ident(name), .. } = tokens.get(*index).unwrap() {
             let var = name.clone();
             *index += 1;
@@ -124,6 +351,16 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
         return Stmt::Return(expr);
     }
 
+    if let Some(Token { kind: TokenKind::Yield, .. }) = tokens.get(*index) {
+        *index += 1;
+        return Stmt::Yield(parse_binary_expr(tokens, index));
+    }
+
+    if let Some(Token { kind: TokenKind::Break, .. }) = tokens.get(*index) {
+        *index += 1;
+        return Stmt::Break;
+    }
+
     if let Some(Token { kind: TokenKind::Loop, .. }) = tokens.get(*index) {
         return parse_loop_stmt(tokens, index);
     }
@@ -138,20 +375,12 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
             *index += 1;
             let cond = parse_binary_expr(tokens, index);
             if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
-                panic!(
-                    "Parse error at line {}, col {}: Expected ')' after if condition",
-                    tokens[*index].line,
-                    tokens[*index].col
-                );
+                fail!(at tokens[*index], "P021");
             }
             *index += 1;
 
             if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. })) {
-                panic!(
-                    "Parse error at line {}, col {}: Expected '{{' after if condition",
-                    tokens[*index].line,
-                    tokens[*index].col
-                );
+                fail!(at tokens[*index], "P022");
             }
             *index += 1;
 
@@ -163,11 +392,7 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
 
             branches.push((Some(cond), body));
         } else {
-            panic!(
-                "Parse error at line {}, col {}: Expected '(' after if",
-                tokens[*index].line,
-                tokens[*index].col
-            );
+            fail!(at tokens[*index], "P023");
         }
 
         // 处理 elif 和 else 分支
@@ -175,29 +400,17 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
             if let Some(Token { kind: TokenKind::Elif, .. }) = tokens.get(*index) {
                 *index += 1;
                 if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LParen, .. })) {
-                    panic!(
-                        "Parse error at line {}, col {}: Expected '(' after elif",
-                        tokens[*index].line,
-                        tokens[*index].col
-                    );
+                    fail!(at tokens[*index], "P024");
                 }
                 *index += 1;
                 let cond = parse_binary_expr(tokens, index);
                 if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
-                    panic!(
-                        "Parse error at line {}, col {}: Expected ')' after elif condition",
-                        tokens[*index].line,
-                        tokens[*index].col
-                    );
+                    fail!(at tokens[*index], "P025");
                 }
                 *index += 1;
 
                 if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. })) {
-                    panic!(
-                        "Parse error at line {}, col {}: Expected '{{' after elif condition",
-                        tokens[*index].line,
-                        tokens[*index].col
-                    );
+                    fail!(at tokens[*index], "P026");
                 }
                 *index += 1;
 
@@ -211,11 +424,7 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
             } else if let Some(Token { kind: TokenKind::Else, .. }) = tokens.get(*index) {
                 *index += 1;
                 if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. })) {
-                    panic!(
-                        "Parse error at line {}, col {}: Expected '{{' after else",
-                        tokens[*index].line,
-                        tokens[*index].col
-                    );
+                    fail!(at tokens[*index], "P027");
                 }
                 *index += 1;
 
@@ -232,20 +441,12 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
                 *index += 1;
                 let cond = parse_binary_expr(tokens, index);
                 if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
-                    panic!(
-                        "Parse error at line {}, col {}: Expected ')' after condition",
-                        tokens[*index].line,
-                        tokens[*index].col
-                    );
+                    fail!(at tokens[*index], "P028");
                 }
                 *index += 1;
 
                 if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. })) {
-                    panic!(
-                        "Parse error at line {}, col {}: Expected '{{' after condition",
-                        tokens[*index].line,
-                        tokens[*index].col
-                    );
+                    fail!(at tokens[*index], "P029");
                 }
                 *index += 1;
 
@@ -274,27 +475,26 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
     }
 
     // ✅ 优先识别函数调用语句
-    if let Some(Token { kind: TokenKind::Ident(id), .. }) = tokens.get(*index) {
-        if matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::LParen, .. })) {
-            let id = id.clone();
-            *index += 1;
-            let args = parse_call_args(tokens, index);
-            return Stmt::Expr(Expr::Call(id, args));
-        }
+    if let Some(Token { kind: TokenKind::Ident(id), .. }) = tokens.get(*index)
+        && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::LParen, .. }))
+        && !is_typed_target(tokens, *index) {
+        let id = id.clone();
+        let site = next_site(&tokens[*index]);
+        *index += 1;
+        let args = parse_call_args(tokens, index);
+        return Stmt::Expr(Expr::Call(id, args, site));
     }
 
     // Postfix increment/decrement: a++ or a--
     if let Some(Token { kind: TokenKind::Ident(name), .. }) = tokens.get(*index) {
-        if matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('+'), .. }))
-            && matches!(tokens.get(*index + 2), Some(Token { kind: TokenKind::Unknown('+'), .. })) {
+        if matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::PlusPlus, .. })) {
             let var = name.clone();
-            *index += 3;
+            *index += 2;
             return Stmt::Increment(var);
         }
-        if matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('-'), .. }))
-            && matches!(tokens.get(*index + 2), Some(Token { kind: TokenKind::Unknown('-'), .. })) {
+        if matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::MinusMinus, .. })) {
             let var = name.clone();
-            *index += 3;
+            *index += 2;
             return Stmt::Decrement(var);
         }
     }
@@ -305,7 +505,7 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
         let lhs_expr = parse_expr(tokens, index);
 
         // 只有当 lhs_expr 为访问属性或数组索引时，才认为是属性赋值
-        if (matches!(lhs_expr, Expr::Access(_, _) | Expr::Index(_, _)))
+        if (matches!(lhs_expr, Expr::Access(..) | Expr::Index(..)))
             && (matches!(tokens.get(*index), Some(Token { kind: TokenKind::Assign, .. }))
                 || matches!(tokens.get(*index), Some(Token { kind: TokenKind::Eq, .. })))
         {
@@ -329,38 +529,22 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
             *index += 1;
             var_type = match tokens.get(*index) {
                 Some(Token { kind: TokenKind::Ident(t), .. }) => Some(t.clone()),
-                _ => panic!(
-                    "Parse error at line {}, col {}: Expected type inside ()",
-                    tokens[*index].line,
-                    tokens[*index].col
-                ),
+                _ => fail!(at tokens[*index], "P030"),
             };
             *index += 1;
             if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
-                panic!(
-                    "Parse error at line {}, col {}: Expected )",
-                    tokens[*index].line,
-                    tokens[*index].col
-                );
+                fail!(at tokens[*index], "P031");
             }
             *index += 1;
         } else if matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBracket, .. })) {
             *index += 1;
             var_type = match tokens.get(*index) {
                 Some(Token { kind: TokenKind::Ident(t), .. }) => Some(t.clone()),
-                _ => panic!(
-                    "Parse error at line {}, col {}: Expected type inside []",
-                    tokens[*index].line,
-                    tokens[*index].col
-                ),
+                _ => fail!(at tokens[*index], "P032"),
             };
             *index += 1;
             if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBracket, .. })) {
-                panic!(
-                    "Parse error at line {}, col {}: Expected ]",
-                    tokens[*index].line,
-                    tokens[*index].col
-                );
+                fail!(at tokens[*index], "P033");
             }
             *index += 1;
         }
@@ -378,27 +562,15 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
                     *index += 1;
                     let const_type = match tokens.get(*index) {
                         Some(Token { kind: TokenKind::Ident(t), .. }) => Some(t.clone()),
-                        _ => panic!(
-                            "Parse error at line {}, col {}: Expected type after :(",
-                            tokens[*index].line,
-                            tokens[*index].col
-                        ),
+                        _ => fail!(at tokens[*index], "P034"),
                     };
                     *index += 1;
                     if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
-                        panic!(
-                            "Parse error at line {}, col {}: Expected ) after constant type",
-                            tokens[*index].line,
-                            tokens[*index].col
-                        );
+                        fail!(at tokens[*index], "P035");
                     }
                     *index += 1;
                     if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Eq, .. })) {
-                        panic!(
-                            "Parse error at line {}, col {}: Expected = after constant type",
-                            tokens[*index].line,
-                            tokens[*index].col
-                        );
+                        fail!(at tokens[*index], "P036");
                     }
                     *index += 1;
                     let expr = parse_binary_expr(tokens, index);
@@ -409,64 +581,89 @@ fn parse_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
                     *index += 1;
                     let var_type = match tokens.get(*index) {
                         Some(Token { kind: TokenKind::Ident(t), .. }) => Some(t.clone()),
-                        _ => panic!(
-                            "Parse error at line {}, col {}: Expected type after :[",
-                            tokens[*index].line,
-                            tokens[*index].col
-                        ),
+                        _ => fail!(at tokens[*index], "P037"),
                     };
                     *index += 1;
                     if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBracket, .. })) {
-                        panic!(
-                            "Parse error at line {}, col {}: Expected ] after variable type",
-                            tokens[*index].line,
-                            tokens[*index].col
-                        );
+                        fail!(at tokens[*index], "P038");
                     }
                     *index += 1;
                     if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Eq, .. })) {
-                        panic!(
-                            "Parse error at line {}, col {}: Expected = after variable type",
-                            tokens[*index].line,
-                            tokens[*index].col
-                        );
+                        fail!(at tokens[*index], "P039");
                     }
                     *index += 1;
                     let expr = parse_binary_expr(tokens, index);
                     return Stmt::VarDecl(name, var_type, expr);
                 }
                 _ => {
-                    panic!(
-                        "Parse error at line {}, col {}: Expected :() or :[] for type declaration",
-                        tokens[*index - 1].line,
-                        tokens[*index - 1].col
-                    );
+                    fail!(at tokens[*index - 1], "P040");
                 }
             }
+        } else if matches!(tokens.get(*index), Some(Token { kind: TokenKind::ForceAssign, .. })) {
+            // 强制转换赋值：x =:: v 或 x(type) =:: v
+            *index += 1;
+            let expr = parse_binary_expr(tokens, index);
+            return Stmt::ForceAssign(name, var_type, expr);
+        } else if matches!(tokens.get(*index), Some(Token { kind: TokenKind::BindOne, .. })) {
+            // 响应式绑定：total => price * qty
+            *index += 1;
+            let expr = parse_binary_expr(tokens, index);
+            return Stmt::Bind(name, expr);
+        } else if matches!(tokens.get(*index), Some(Token { kind: TokenKind::BindTwo, .. })) {
+            // 双向绑定：a <=> b
+            *index += 1;
+            let other = match tokens.get(*index) {
+                Some(Token { kind: TokenKind::Ident(o), .. }) => o.clone(),
+                _ => fail!(at tokens[*index], "P041"),
+            };
+            *index += 1;
+            return Stmt::BindTwo(name, other);
         } else if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Eq, .. })) {
             // 普通变量赋值（允许 a = 加法🧮(a, b)）
             *index += 1;
             let expr = parse_binary_expr(tokens, index);
             return Stmt::Assign(name, expr);
         } else {
-            println!("当前 Token：{:?}", tokens.get(*index));
-            panic!(
-                "Parse error at line {}, col {}: Expected := after variable name",
-                tokens[*index].line,
-                tokens[*index].col
-            );
+            fail!(at tokens[*index], "P042");
         }
     }
 
 
-    panic!(
-        "Parse error at line {}, col {}: Unknown statement",
-        tokens[*index].line,
-        tokens[*index].col
-    )
+    fail!(at tokens[*index], "P043")
 }
 
+// 优先级从低到高：?? < || < && < 比较与算术
 fn parse_binary_expr(tokens: &[Token], index: &mut usize) -> Expr {
+    let mut expr = parse_or_expr(tokens, index);
+    while matches!(tokens.get(*index), Some(Token { kind: TokenKind::QuestionQuestion, .. })) {
+        *index += 1;
+        let rhs = parse_or_expr(tokens, index);
+        expr = Expr::Coalesce(Box::new(expr), Box::new(rhs));
+    }
+    expr
+}
+
+fn parse_or_expr(tokens: &[Token], index: &mut usize) -> Expr {
+    let mut expr = parse_and_expr(tokens, index);
+    while matches!(tokens.get(*index), Some(Token { kind: TokenKind::OrOr, .. })) {
+        *index += 1;
+        let rhs = parse_and_expr(tokens, index);
+        expr = Expr::Logical("||".to_string(), Box::new(expr), Box::new(rhs));
+    }
+    expr
+}
+
+fn parse_and_expr(tokens: &[Token], index: &mut usize) -> Expr {
+    let mut expr = parse_compare_expr(tokens, index);
+    while matches!(tokens.get(*index), Some(Token { kind: TokenKind::AndAnd, .. })) {
+        *index += 1;
+        let rhs = parse_compare_expr(tokens, index);
+        expr = Expr::Logical("&&".to_string(), Box::new(expr), Box::new(rhs));
+    }
+    expr
+}
+
+fn parse_compare_expr(tokens: &[Token], index: &mut usize) -> Expr {
     // 支持多重比较和多重等式判断 a > b > c, a = b = c, a < b < c
     let mut exprs = Vec::new();
     let mut ops = Vec::new();
@@ -477,65 +674,80 @@ fn parse_binary_expr(tokens: &[Token], index: &mut usize) -> Expr {
     // 不断尝试读取运算符和右侧表达式
     while let Some(op_token) = tokens.get(*index) {
 
-        // 检测两字符运算符 <=, >=, ==, &&, ||
-        let op_str = if matches!(op_token, Token { kind: TokenKind::Unknown('<'), .. })
-            && (matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('='), .. }))
-                 || matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Eq, .. })))
-        {
-            *index += 2;
-            "<=".to_string()
-        } else if matches!(op_token, Token { kind: TokenKind::Unknown('>'), .. })
-            && (matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('='), .. }))
-                 || matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Eq, .. })))
-        {
-            *index += 2;
-            ">=".to_string()
-        } else if matches!(op_token, Token { kind: TokenKind::Eq, .. })
-            && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Eq, .. }))
-        {
-            *index += 2;
-            "==".to_string()
-        } else if matches!(op_token, Token { kind: TokenKind::Unknown('&'), .. })
-            && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('&'), .. }))
-        {
-            *index += 2;
-            "&&".to_string()
-        } else if matches!(op_token, Token { kind: TokenKind::Unknown('|'), .. })
-            && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('|'), .. }))
-        {
-            *index += 2;
-            "||".to_string()
-        } else {
-            // 单字符运算符
-            let single = match op_token.kind {
-                TokenKind::Unknown('+') => "+",
-                TokenKind::Unknown('-') => "-",
-                TokenKind::Unknown('*') => "*",
-                TokenKind::Unknown('/') => "/",
-                TokenKind::Unknown('>') => ">",
-                TokenKind::Unknown('<') => "<",
-                TokenKind::Unknown('&') => "&",
-                TokenKind::Unknown('|') => "|",
-                TokenKind::Eq         => "=",
-                _ => {
-                    break;
-                }
+        // 区间：a..b / a..=b / a..b:step
+        if matches!(op_token.kind, TokenKind::DotDot | TokenKind::DotDotEq) {
+            let inclusive = matches!(op_token.kind, TokenKind::DotDotEq);
+            let site = next_site(op_token);
+            *index += 1;
+            let end = parse_arith_expr(tokens, index);
+            // `:name{` / `:(i, name){` after a range is the loop binding, not a step
+            let step = if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. }))
+                && !is_loop_binding(tokens, *index + 1)
+            {
+                *index += 1;
+                Some(Box::new(parse_arith_expr(tokens, index)))
+            } else {
+                None
             };
+            let start = exprs.pop().unwrap();
+            exprs.push(Expr::Range { start: Box::new(start), end: Box::new(end), inclusive, step, site });
+            continue;
+        }
+
+        // 变体匹配：value is Enum.Variant(bindings)
+        if matches!(op_token, Token { kind: TokenKind::Ident(kw), .. } if kw == "is") {
             *index += 1;
-            single.to_string()
-        };
+            let (enum_name, variant, bindings) = match parse_expr(tokens, index) {
+                Expr::Variant(e, v, args) => {
+                    let names = args.into_iter().map(|a| match a {
+                        Expr::Ident(n) => n,
+                        other => fail!(at op_token, "P044", format!("{:?}", other)),
+                    }).collect();
+                    (e, v, names)
+                }
+                Expr::Access(base, v, _) => match *base {
+                    Expr::Ident(e) => (e, v, Vec::new()),
+                    _ => fail!(at op_token, "P045"),
+                },
+                _ => fail!(at op_token, "P045"),
+            };
+            let left = exprs.pop().unwrap();
+            exprs.push(Expr::Is(Box::new(left), enum_name, variant, bindings));
+            continue;
+        }
+
+        // && / || 以及其他 token 由外层处理
+        let op_str = match op_token.kind {
+            TokenKind::Plus  => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star  => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Gt    => ">",
+            TokenKind::Lt    => "<",
+            TokenKind::Ge    => ">=",
+            TokenKind::Le    => "<=",
+            TokenKind::EqEq  => "==",
+            TokenKind::BangEq => "!=",
+            TokenKind::Eq    => "=",
+            TokenKind::Amp   => "&",
+            TokenKind::Bar   => "|",
+            _ => break,
+        }.to_string();
+        *index += 1;
+
+        let site = next_site(op_token);
 
         // 解析右侧表达式
         let next_expr = parse_expr(tokens, index);
 
         // 判断是否为比较或逻辑运算符，以决定是否链式
-        if ["<", ">", "=", "<=", ">=", "==", "&&", "||"].contains(&op_str.as_str()) {
+        if ["<", ">", "=", "<=", ">=", "==", "!="].contains(&op_str.as_str()) {
             exprs.push(next_expr);
-            ops.push(op_str);
+            ops.push((op_str, site));
         } else {
             // 普通算术：立刻构建节点
             let left = exprs.pop().unwrap();
-            exprs.push(Expr::Binary(Box::new(left), op_str, Box::new(next_expr)));
+            exprs.push(Expr::Binary(Box::new(left), op_str, Box::new(next_expr), site));
         }
     }
 
@@ -547,24 +759,45 @@ fn parse_binary_expr(tokens: &[Token], index: &mut usize) -> Expr {
     // 构造链式比较 ((a>b)&&(b>c)&&…)
     let mut result = Expr::Binary(
         Box::new(exprs[0].clone()),
-        ops[0].clone(),
+        ops[0].0.clone(),
         Box::new(exprs[1].clone()),
+        ops[0].1,
     );
     for i in 1..ops.len() {
         let cmp = Expr::Binary(
             Box::new(exprs[i].clone()),
-            ops[i].clone(),
+            ops[i].0.clone(),
             Box::new(exprs[i + 1].clone()),
+            ops[i].1,
         );
         result = Expr::Logical("&&".to_string(), Box::new(result), Box::new(cmp));
     }
     result
 }
 
+// Range bounds and steps: arithmetic only, so `0..n - 1:2` stops at ':'
+fn parse_arith_expr(tokens: &[Token], index: &mut usize) -> Expr {
+    let mut expr = parse_expr(tokens, index);
+    while let Some(tok) = tokens.get(*index) {
+        let op = match tok.kind {
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Star => "*",
+            TokenKind::Slash => "/",
+            _ => break,
+        }.to_string();
+        let site = next_site(tok);
+        *index += 1;
+        let rhs = parse_expr(tokens, index);
+        expr = Expr::Binary(Box::new(expr), op, Box::new(rhs), site);
+    }
+    expr
+}
+
 fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
     let mut expr = match tokens.get(*index) {
         // Unary logical NOT: !expr
-        Some(Token { kind: TokenKind::Unknown('!'), .. }) => {
+        Some(Token { kind: TokenKind::Bang, .. }) => {
             // consume '!'
             *index += 1;
             // parse the next expression
@@ -572,7 +805,8 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
             return Expr::Not(Box::new(inner));
         }
         // Unary minus: -expr or -number
-        Some(Token { kind: TokenKind::Unknown('-'), .. }) => {
+        Some(tok @ Token { kind: TokenKind::Minus, .. }) => {
+            let site = next_site(tok);
             *index += 1; // skip '-'
             // If next token is a number literal, negate directly
             if let Some(Token { kind: TokenKind::Number(n), .. }) = tokens.get(*index) {
@@ -581,7 +815,7 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
             } else {
                 // Otherwise parse the inner expression and represent as 0 - expr
                 let rhs = parse_expr(tokens, index);
-                return Expr::Binary(Box::new(Expr::Number(0.0)), "-".to_string(), Box::new(rhs));
+                return Expr::Binary(Box::new(Expr::Number(NumLit::Int(0))), "-".to_string(), Box::new(rhs), site);
             }
         }
         // Parentheses grouping: (expr)
@@ -592,15 +826,11 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
             let inner = parse_binary_expr(tokens, index);
             // Expect ')'
             if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
-                panic!(
-                    "Parse error at line {}, col {}: Expected ')' to close grouping",
-                    tokens[*index].line,
-                    tokens[*index].col
-                );
+                fail!(at tokens[*index], "P046");
             }
             *index += 1;
-            // Return grouped expr
-            return inner;
+            // Grouped expr still takes suffixes: (0..10).>map(f)
+            inner
         }
         Some(Token { kind: TokenKind::Number(n), .. }) => {
             *index += 1;
@@ -631,11 +861,11 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                 let key = match tokens.get(*index) {
                     Some(Token { kind: TokenKind::Ident(k), .. }) => k.clone(),
                     Some(Token { kind: TokenKind::Str(s), .. }) => s.clone(),
-                    _ => panic!("Expected key in object literal"),
+                    _ => fail!("P047"),
                 };
                 *index += 1;
                 if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. })) {
-                    panic!("Expected ':' after object key");
+                    fail!("P048");
                 }
                 *index += 1;
                 let value = parse_expr(tokens, index);
@@ -652,7 +882,7 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
             *index += 1;
             // Input expression: I>[prompt?, type?, limit?]
             if name == "I"
-                && matches!(tokens.get(*index), Some(Token { kind: TokenKind::Unknown('>'), .. }))
+                && matches!(tokens.get(*index), Some(Token { kind: TokenKind::Gt, .. }))
                 && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::LBracket, .. }))
             {
                 // consume '>' and '['
@@ -688,26 +918,48 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
             }
             // Handle function call
             if matches!(tokens.get(*index), Some(Token { kind: TokenKind::LParen, .. })) {
+                let site = next_site(&tokens[*index - 1]);
                 let args = parse_call_args(tokens, index);
-                Expr::Call(name, args)
+                Expr::Call(name, args, site)
+            } else if is_construct_start(tokens, *index) {
+                // Record construction: Point{x: 1, y: 2}
+                *index += 1;
+                let mut fields = Vec::new();
+                while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBrace, .. })) {
+                    let key = match tokens.get(*index) {
+                        Some(Token { kind: TokenKind::Ident(k), .. }) => k.clone(),
+                        Some(Token { kind: TokenKind::Str(s), .. }) => s.clone(),
+                        _ => fail!(at tokens[*index], "P049", name),
+                    };
+                    *index += 1;
+                    if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. })) {
+                        fail!(at tokens[*index], "P050");
+                    }
+                    *index += 1;
+                    let value = parse_binary_expr(tokens, index);
+                    fields.push((key, Box::new(value)));
+                    if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Comma, .. })) {
+                        *index += 1;
+                    }
+                }
+                *index += 1;
+                Expr::Construct(name, fields)
             } else {
                 Expr::Ident(name)
             }
         }
         _ => {
             if let Some(tok) = tokens.get(*index) {
-                panic!(
-                    "Parse error at line {}, col {}: Unsupported expression token {:?}",
-                    tok.line, tok.col, tok.kind
-                );
+                fail!(at tok, "P051", tok.kind);
             } else {
-                panic!("Parse error: Unexpected end of input while parsing expression");
+                fail!("P052");
             }
         }
     };
 
     // Unified suffix parsing for all expr:
     loop {
+        let at = *index;
         match tokens.get(*index) {
             // Pipe-only method
             Some(Token { kind: TokenKind::Pipe, .. })
@@ -723,11 +975,7 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                     *index += 1;
                     s
                 } else {
-                    panic!(
-                        "Parse error at line {}, col {}: Expected method name after '|'",
-                        tokens[*index].line,
-                        tokens[*index].col
-                    );
+                    fail!(at tokens[*index], "P053");
                 };
                 // optional args
                 let mut margs = Vec::new();
@@ -742,11 +990,7 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                         }
                     }
                     if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
-                        panic!(
-                            "Parse error at line {}, col {}: Expected ')' after method args",
-                            tokens[*index].line,
-                            tokens[*index].col
-                        );
+                        fail!(at tokens[*index], "P054");
                     }
                     *index += 1;
                 }
@@ -754,15 +998,47 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                     target: Box::new(expr),
                     name: mname,
                     args: margs,
+                    site: next_site(&tokens[at]),
                 };
                 continue;
             }
+            // a?.>method(args) —— 接收者为 null 时整个调用结果为 null
+            Some(Token { kind: TokenKind::QuestionPipe, .. }) => {
+                *index += 1;
+                let mname = match tokens.get(*index) {
+                    Some(Token { kind: TokenKind::Ident(id), .. }) => {
+                        let s = id.clone();
+                        *index += 1;
+                        s
+                    }
+                    _ => fail!(at tokens[*index], "P055"),
+                };
+                let margs = parse_call_args(tokens, index);
+                expr = Expr::SafeMethodCall {
+                    target: Box::new(expr),
+                    name: mname,
+                    args: margs,
+                    site: next_site(&tokens[at]),
+                };
+                continue;
+            }
+            // a?.b
+            Some(Token { kind: TokenKind::QuestionDot, .. }) => {
+                *index += 1;
+                let prop = match tokens.get(*index) {
+                    Some(Token { kind: TokenKind::Ident(p), .. }) => p.clone(),
+                    _ => fail!("P056"),
+                };
+                *index += 1;
+                expr = Expr::SafeAccess(Box::new(expr), prop, next_site(&tokens[at]));
+                continue;
+            }
             // .>method or .>method(args)
             Some(Token { kind: TokenKind::Dot, .. })
                 if matches!(
                     tokens.get(*index + 1),
-                    Some(Token { kind: TokenKind::Unknown('>'), .. })
-                    | Some(Token { kind: TokenKind::Pipe,    .. })
+                    Some(Token { kind: TokenKind::Gt, .. })
+                    | Some(Token { kind: TokenKind::Pipe, .. })
                 ) =>
             {
                 *index += 2; // skip '.' and '>' or '.|'
@@ -773,10 +1049,7 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                         *index += 1;
                         s
                     }
-                    _ => panic!(
-                        "Parse error at line {}, col {}: Expected method name after .>",
-                        tokens[*index].line, tokens[*index].col
-                    ),
+                    _ => fail!(at tokens[*index], "P057"),
                 };
 
                 // optional argument list
@@ -800,10 +1073,7 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                     if !matches!(tokens.get(*index),
                                  Some(Token { kind: TokenKind::RParen, .. }))
                     {
-                        panic!(
-                            "Parse error at line {}, col {}: Expected ')' after arguments",
-                            tokens[*index].line, tokens[*index].col
-                        );
+                        fail!(at tokens[*index], "P058");
                     }
                     *index += 1; // skip ')'
                 }
@@ -813,6 +1083,7 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                     target: Box::new(expr),
                     name: mname,
                     args: margs,
+                    site: next_site(&tokens[at]),
                 };
                 continue;
             }
@@ -821,10 +1092,17 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                 *index += 1;
                 let prop = match tokens.get(*index) {
                     Some(Token { kind: TokenKind::Ident(p), .. }) => p.clone(),
-                    _ => panic!("Expected property name after '.'"),
+                    _ => fail!(at tokens[*index - 1], "P059"),
                 };
                 *index += 1;
-                expr = Expr::Access(Box::new(expr), prop);
+                // Enum.Variant(payload...)
+                if let Expr::Ident(enum_name) = &expr
+                    && matches!(tokens.get(*index), Some(Token { kind: TokenKind::LParen, .. })) {
+                    let args = parse_call_args(tokens, index);
+                    expr = Expr::Variant(enum_name.clone(), prop, args);
+                    continue;
+                }
+                expr = Expr::Access(Box::new(expr), prop, next_site(&tokens[at]));
                 continue;
             }
             // array index  []  (append handled above)
@@ -836,21 +1114,39 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
                     expr = Expr::Index(
                         Box::new(expr),
                         Box::new(Expr::Ident("_append".into())),
+                        next_site(&tokens[at]),
                     );
                 } else {
                     *index += 1; // skip '['
-                    let idx = parse_binary_expr(tokens, index);
+                    // 切片 s[a:b]，两端都可省略
+                    let idx = if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. })) {
+                        None
+                    } else {
+                        Some(parse_binary_expr(tokens, index))
+                    };
+                    if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. })) {
+                        *index += 1; // skip ':'
+                        let end = if matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBracket, .. })) {
+                            None
+                        } else {
+                            Some(Box::new(parse_binary_expr(tokens, index)))
+                        };
+                        if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBracket, .. })) {
+                            fail!(at tokens[*index], "P060");
+                        }
+                        *index += 1; // skip ']'
+                        expr = Expr::Slice(Box::new(expr), idx.map(Box::new), end, next_site(&tokens[at]));
+                        continue;
+                    }
+                    let idx = idx.expect("index expression");
                     if !matches!(
                         tokens.get(*index),
                         Some(Token { kind: TokenKind::RBracket, .. })
                     ) {
-                        panic!(
-                            "Parse error at line {}, col {}: Expected ']' after index",
-                            tokens[*index].line, tokens[*index].col
-                        );
+                        fail!(at tokens[*index], "P061");
                     }
                     *index += 1; // skip ']'
-                    expr = Expr::Index(Box::new(expr), Box::new(idx));
+                    expr = Expr::Index(Box::new(expr), Box::new(idx), next_site(&tokens[at]));
                 }
                 continue;
             }
@@ -859,14 +1155,12 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
     }
     // support postfix ++/--
     if let Expr::Ident(name) = &expr {
-        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Unknown('+'), .. }))
-            && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('+'), .. })) {
-            *index += 2;
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::PlusPlus, .. })) {
+            *index += 1;
             return Expr::PostfixIncrement(name.clone());
         }
-        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Unknown('-'), .. }))
-            && matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::Unknown('-'), .. })) {
-            *index += 2;
+        if matches!(tokens.get(*index), Some(Token { kind: TokenKind::MinusMinus, .. })) {
+            *index += 1;
             return Expr::PostfixDecrement(name.clone());
         }
     }
@@ -875,58 +1169,215 @@ fn parse_expr(tokens: &[Token], index: &mut usize) -> Expr {
 
 
 
+// `name(type) :=` / `name(type) =::` declare or convert rather than call
+fn is_typed_target(tokens: &[Token], index: usize) -> bool {
+    matches!(tokens.get(index + 2), Some(Token { kind: TokenKind::Ident(_), .. }))
+        && matches!(tokens.get(index + 3), Some(Token { kind: TokenKind::RParen, .. }))
+        && matches!(tokens.get(index + 4), Some(Token { kind: TokenKind::Assign | TokenKind::ForceAssign, .. }))
+}
+
+// `Name{` starts a record construction only when followed by `}` or `field:`,
+// so blocks such as `L>[n{ print(n) }` keep parsing as before.
+fn is_construct_start(tokens: &[Token], index: usize) -> bool {
+    if !matches!(tokens.get(index), Some(Token { kind: TokenKind::LBrace, .. })) {
+        return false;
+    }
+    match tokens.get(index + 1) {
+        Some(Token { kind: TokenKind::RBrace, .. }) => true,
+        Some(Token { kind: TokenKind::Ident(_) | TokenKind::Str(_), .. }) => {
+            if !matches!(tokens.get(index + 2), Some(Token { kind: TokenKind::Colon, .. })) {
+                return false;
+            }
+            // `name:(type)= value` / `name:[type]= value` is a declaration, not a field
+            let typed_decl = matches!(tokens.get(index + 3), Some(Token { kind: TokenKind::LParen | TokenKind::LBracket, .. }))
+                && matches!(tokens.get(index + 4), Some(Token { kind: TokenKind::Ident(_), .. }))
+                && matches!(tokens.get(index + 5), Some(Token { kind: TokenKind::RParen | TokenKind::RBracket, .. }))
+                && matches!(tokens.get(index + 6), Some(Token { kind: TokenKind::Eq, .. }));
+            !typed_decl
+        }
+        _ => false,
+    }
+}
+
 fn parse_call_args(tokens: &[Token], index: &mut usize) -> Vec<Expr> {
     let mut args = Vec::new();
     if matches!(tokens.get(*index), Some(Token { kind: TokenKind::LParen, .. })) {
         *index += 1;
         while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
             args.push(parse_binary_expr(tokens, index));
-            if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Comma, .. })) {
-                *index += 1;
+            // 实参之间必须有逗号：print(x--3) 不能被读成 x-- 和 3 两个实参
+            match tokens.get(*index) {
+                Some(Token { kind: TokenKind::Comma, .. }) => *index += 1,
+                Some(Token { kind: TokenKind::RParen, .. }) => {}
+                Some(_) => fail!(at tokens[*index], "P058"),
+                None => fail!("P058"),
             }
         }
         *index += 1;
     }
     args
 }
+// `L>[1, 2]:x` iterates an array literal; `L>[n]` and `L>[n]:i` repeat n times
+fn is_array_literal_header(tokens: &[Token], index: usize) -> bool {
+    let mut depth = 0;
+    for tok in &tokens[index..] {
+        match tok.kind {
+            TokenKind::LBracket | TokenKind::LParen | TokenKind::LBrace => depth += 1,
+            TokenKind::RBracket | TokenKind::RParen | TokenKind::RBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    return false;
+                }
+            }
+            TokenKind::Comma if depth == 1 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+// `iterable:item` or `iterable:(i, item)`
+fn parse_foreach_header(tokens: &[Token], index: &mut usize) -> (Option<String>, String, Expr) {
+    let iterable = parse_binary_expr(tokens, index);
+    if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. })) {
+        fail!("P062");
+    }
+    *index += 1;
+    let (index_var, item) = match tokens.get(*index) {
+        Some(Token { kind: TokenKind::Ident(var), .. }) => {
+            *index += 1;
+            (None, var.clone())
+        }
+        Some(Token { kind: TokenKind::LParen, .. }) if is_loop_binding(tokens, *index) => {
+            let (Some(Token { kind: TokenKind::Ident(i), .. }), Some(Token { kind: TokenKind::Ident(var), .. })) =
+                (tokens.get(*index + 1), tokens.get(*index + 3))
+            else {
+                unreachable!()
+            };
+            *index += 5;
+            (Some(i.clone()), var.clone())
+        }
+        _ => fail!("P063"),
+    };
+    (index_var, item, iterable)
+}
+
+// Statements up to the closing '}' (the opening '{' is already consumed)
+fn parse_block_body(tokens: &[Token], index: &mut usize) -> Vec<Stmt> {
+    let mut body = Vec::new();
+    while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBrace, .. })) {
+        if tokens.get(*index).is_none() {
+            fail!("P064");
+        }
+        body.push(parse_stmt(tokens, index));
+    }
+    *index += 1;
+    body
+}
+
+// A parenthesized loop condition: (cond)
+fn parse_loop_condition(tokens: &[Token], index: &mut usize) -> Expr {
+    if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LParen, .. })) {
+        fail!("P065");
+    }
+    *index += 1;
+    let cond = parse_binary_expr(tokens, index);
+    if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. })) {
+        fail!("P066");
+    }
+    *index += 1;
+    cond
+}
+
+// The binding after `:` in a for-each header: `name{` or `(i, name){`
+fn is_loop_binding(tokens: &[Token], index: usize) -> bool {
+    match tokens.get(index) {
+        Some(Token { kind: TokenKind::Ident(_), .. }) => {
+            matches!(tokens.get(index + 1), Some(Token { kind: TokenKind::LBrace, .. }))
+        }
+        Some(Token { kind: TokenKind::LParen, .. }) => {
+            matches!(tokens.get(index + 1), Some(Token { kind: TokenKind::Ident(_), .. }))
+                && matches!(tokens.get(index + 2), Some(Token { kind: TokenKind::Comma, .. }))
+                && matches!(tokens.get(index + 3), Some(Token { kind: TokenKind::Ident(_), .. }))
+                && matches!(tokens.get(index + 4), Some(Token { kind: TokenKind::RParen, .. }))
+                && matches!(tokens.get(index + 5), Some(Token { kind: TokenKind::LBrace, .. }))
+        }
+        _ => false,
+    }
+}
+
 // 新的循环语句解析函数，支持多种循环格式
 fn parse_loop_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
     use crate::ast::LoopKind;
 
     if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Loop, .. })) {
-        panic!("Expected Loop");
+        fail!("P067");
     }
     *index += 1;
 
     let loop_kind = match tokens.get(*index) {
-        Some(Token { kind: TokenKind::LBracket, .. }) => {
+        Some(Token { kind: TokenKind::LBracket, .. }) if !is_array_literal_header(tokens, *index) => {
             *index += 1;
             let expr = parse_binary_expr(tokens, index);
             // Accept either ] or { directly after the expr, for L>[10]{...}
             if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBracket, .. }))
                 && !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. }))
             {
-                panic!("Expected ']' or '{{' after loop expression");
+                fail!("P068");
             }
+            let mut counter = None;
             if matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBracket, .. })) {
                 *index += 1;
+                // L>[n]:i 具名计数器
+                if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. })) {
+                    *index += 1;
+                    counter = match tokens.get(*index) {
+                        Some(Token { kind: TokenKind::Ident(var), .. }) => Some(var.clone()),
+                        _ => fail!("P069"),
+                    };
+                    *index += 1;
+                }
             }
-            LoopKind::Times(expr)
+            LoopKind::Times(expr, counter)
         }
-        Some(Token { kind: TokenKind::Ident(data), .. }) => {
-            let data = data.clone();
+        // L>{ ... } 无限循环；循环体后的 :(cond) / :!(cond) 为后置条件，与换行无关
+        Some(Token { kind: TokenKind::LBrace, .. }) => {
             *index += 1;
-            if let Some(Token { kind: TokenKind::Colon, .. }) = tokens.get(*index) {
-                *index += 1;
-                let item = match tokens.get(*index) {
-                    Some(Token { kind: TokenKind::Ident(var), .. }) => var.clone(),
-                    _ => panic!("Expected variable name after ':'"),
-                };
-                *index += 1;
-                LoopKind::ForEach(item, Expr::Ident(data))
-            } else {
-                panic!("Expected ':' after iterable identifier");
+            let body = parse_block_body(tokens, index);
+            if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Colon, .. })) {
+                return Stmt::Loop(LoopKind::Infinite, body);
             }
+            *index += 1;
+            let kind = match tokens.get(*index) {
+                Some(Token { kind: TokenKind::LParen, .. }) => LoopKind::DoWhile(parse_loop_condition(tokens, index)),
+                Some(Token { kind: TokenKind::Bang, .. })
+                    if matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::LParen, .. })) =>
+                {
+                    *index += 1;
+                    LoopKind::DoUntil(parse_loop_condition(tokens, index))
+                }
+                Some(tok) => fail!(at tok, "P076"),
+                None => fail!("P076"),
+            };
+            return Stmt::Loop(kind, body);
+        }
+        // L>!(cond){ ... } 直到条件成立
+        Some(Token { kind: TokenKind::Bang, .. })
+            if matches!(tokens.get(*index + 1), Some(Token { kind: TokenKind::LParen, .. })) =>
+        {
+            *index += 1;
+            LoopKind::Until(parse_loop_condition(tokens, index))
+        }
+        // L>||data:item 并行遍历
+        Some(Token { kind: TokenKind::OrOr, .. }) => {
+            *index += 1;
+            let (index_var, item, iterable) = parse_foreach_header(tokens, index);
+            LoopKind::ParallelForEach(index_var, item, iterable)
+        }
+        // L>data:item / L>makeArr(1,2,3):x / L>1..10:i / L>obj:(key, value)
+        Some(tok) if !matches!(tok.kind, TokenKind::LParen) => {
+            let (index_var, item, iterable) = parse_foreach_header(tokens, index);
+            LoopKind::ForEach(index_var, item, iterable)
         }
         Some(Token { kind: TokenKind::LParen, .. }) => {
             // Decide While vs For by counting semicolons up to the matching RParen
@@ -939,8 +1390,7 @@ fn parse_loop_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
                         depth -= 1;
                         if depth == 0 { break; }
                     }
-                    Some(Token { kind: TokenKind::Semicolon, .. }) 
-                    | Some(Token { kind: TokenKind::Unknown(';'), .. }) if depth == 1 => semi_count += 1,
+                    Some(Token { kind: TokenKind::Semicolon, .. }) if depth == 1 => semi_count += 1,
                     _ => {}
                 }
             }
@@ -956,19 +1406,15 @@ fn parse_loop_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
                 {
                     parts.push(parse_binary_expr(tokens, index));
                     // Only advance on semicolon if present; do not require it
-                    if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Semicolon, .. })) 
-                        || matches!(tokens.get(*index), Some(Token { kind: TokenKind::Unknown(';'), .. })) {
+                    if matches!(tokens.get(*index), Some(Token { kind: TokenKind::Semicolon, .. })) {
                         *index += 1;
                     } else if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RParen, .. }))
                         && !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. }))
                     {
-                        // Enhance debug info before panicking
-                        println!("DEBUG parse_loop_stmt unexpected token at index {}: {:?}", index, tokens.get(*index));
-                        if let Some(tok) = tokens.get(*index) {
-                            println!("  Token detail - kind: {:?}, line: {}, col: {}", tok.kind, tok.line, tok.col);
+                        match tokens.get(*index) {
+                            Some(tok) => fail!(at tok, "P070"),
+                            None => fail!("P070"),
                         }
-                        println!("  Next tokens: {:?}", tokens.get(*index+1));
-                        panic!("Expected ';', ')', or '{{' in loop header");
                     }
                 }
                 // Accept closing ) if present
@@ -976,7 +1422,7 @@ fn parse_loop_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
                     *index += 1;
                 }
                 if parts.len() != 1 {
-                    panic!("Invalid while-loop header: expected 1 condition expression");
+                    fail!("P071");
                 }
                 LoopKind::While(parts.remove(0))
             } else {
@@ -987,16 +1433,14 @@ fn parse_loop_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
                     Box::new(stmt)
                 };
                 // require semicolon
-                if !(matches!(tokens.get(*index), Some(Token { kind: TokenKind::Semicolon, .. })) 
-                    || matches!(tokens.get(*index), Some(Token { kind: TokenKind::Unknown(';'), .. }))) {
-                    panic!("Expected ';' after init in for-loop header");
+                if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Semicolon, .. })) {
+                    fail!("P072");
                 }
                 *index += 1;
                 // parse condition
                 let cond = parse_binary_expr(tokens, index);
-                if !(matches!(tokens.get(*index), Some(Token { kind: TokenKind::Semicolon, .. })) 
-                    || matches!(tokens.get(*index), Some(Token { kind: TokenKind::Unknown(';'), .. }))) {
-                    panic!("Expected ';' after condition in for-loop header");
+                if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::Semicolon, .. })) {
+                    fail!("P073");
                 }
                 *index += 1;
                 // parse step statement
@@ -1011,20 +1455,16 @@ fn parse_loop_stmt(tokens: &[Token], index: &mut usize) -> Stmt {
                 LoopKind::For(init, cond, step)
             }
         }
-        _ => panic!("Unknown loop format"),
+        _ => fail!("P074"),
     };
 
     // Accept either '{' after loop header, or error
     if !matches!(tokens.get(*index), Some(Token { kind: TokenKind::LBrace, .. })) {
-        panic!("Expected '{{' after loop header");
+        fail!("P075");
     }
     *index += 1;
 
-    let mut body = Vec::new();
-    while !matches!(tokens.get(*index), Some(Token { kind: TokenKind::RBrace, .. })) {
-        body.push(parse_stmt(tokens, index));
-    }
-    *index += 1;
+    let body = parse_block_body(tokens, index);
 
     Stmt::Loop(loop_kind, body)
 }
\ No newline at end of file
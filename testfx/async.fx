@@ -0,0 +1,68 @@
+// 协程、事件循环与定时器
+F>worker(name, delay){
+  print(name, "start")
+  sleep(delay)
+  print(name, "done after", delay)
+  R>delay * 10
+}
+
+F>tick(){
+  print("tick")
+}
+
+// 生成器里的 sleep 只挂起拉取它的任务
+F>ticks(n, delay){
+  L>[n]:i{
+    sleep(delay)
+    Y>i
+  }
+}
+
+F>counter(name, delay){
+  L>ticks(3, delay):i{ print(name, i) }
+  R>name
+}
+
+F>waitFor(t){
+  R>await(t)
+}
+
+// 两个任务互相等待：内层的等待报 R102，外层拿到它的结果
+F>pairWait(ch, name){
+  other := recv(ch)
+  r := try(waitFor, other)
+  print(name, "waited:", r.error ?? r.value)
+  R>name
+}
+
+F>hello(){
+  print("after 15ms")
+}
+
+F>main(){
+  a := spawn(worker, "A", 20)
+  b := spawn(worker, "B", 10)
+  print("spawned")
+  print("B result:", await(b))
+  print("A result:", a.>await)
+
+  after(15, hello)
+  t := every(10, tick)
+  sleep(35)
+  cancel(t)
+
+  // C 在 10/20/30ms，D 在 25/50/75ms：C 0, C 1, D 0, C 2, D 1, D 2
+  c := spawn(counter, "C", 10)
+  d := spawn(counter, "D", 25)
+  print(await(c), await(d))
+
+  // 等一个永远不会结束的任务是死锁错误
+  ch := chan()
+  p := spawn(pairWait, ch, "p")
+  q := spawn(pairWait, ch, "q")
+  send(ch, q)
+  send(ch, p)
+  print(await(p), await(q))   // q waited: R102, p waited: q
+  print(await("<task#1>"))      // 只是字符串，不是任务
+  print("main done")
+}
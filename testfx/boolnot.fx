@@ -1,27 +1,48 @@
 F>main(){
+  // 真值规则：false、数值 0、""、空数组/对象、<undef> 为假，其余为真
+  // 每行注释为期望输出
+
   // 布尔文字
-  print("!true =", !true)
-  print("!false =", !false)
+  print("!true =", !true)           // !true = false
+  print("!false =", !false)         // !false = true
+  print("true =", true)             // true = true
 
   // 数值：0 视为假，非 0 视为真
-  print("!0 =", !0)
-  print("!1 =", !1)
-  print("!-5 =", !-5)
+  print("!0 =", !0)                 // !0 = true
+  print("!1 =", !1)                 // !1 = false
+  print("!-5 =", !-5)               // !-5 = false
+  print("!0.0 =", !0.0)             // !0.0 = true
+
+  // 字符串：""、"false" 与零值文本（"0"、"0.0"）视为假，其它非空视为真；不做 trim
+  print("!\"\" =", !"")             // !"" = true
+  print("!\"false\" =", !"false")   // !"false" = true
+  print("!\"0.0\" =", !"0.0")       // !"0.0" = true
+  print("!\" \" =", !" ", !"FALSE") // !" " = false false
+  print("!\"hello\" =", !"hello")   // !"hello" = false
 
-  // 字符串："" 或 "false" 视为假，其它非空视为真
-  print("!\"\" =", !"")
-  print("!\"false\" =", !"false")
-  print("!\"hello\" =", !"hello")
+  // 空数组为假
+  print("![] =", ![])               // ![] = true
+  print("![1] =", ![1])             // ![1] = false
 
   // 变量混合测试
   x := 0
   y := "foo"
   z := false
-  print("!x =", !x)
-  print("!y =", !y)
-  print("!z =", !z)
+  b(bool) := 1
+  print("!x =", !x)                 // !x = true
+  print("!y =", !y)                 // !y = false
+  print("!z =", !z)                 // !z = true
+  print("z =", z, "b =", b)         // z = false b = true
+  print("!!y =", !!y)               // !!y = true
 
   // 复合表达式
-  print("!(1<2) =", !(1<2))
-  print("!(0||true) =", !(0||true))
-}
\ No newline at end of file
+  print("!(1<2) =", !(1<2))         // !(1<2) = false
+  print("!(0||true) =", !(0||true)) // !(0||true) = false
+
+  // if 与 ! 使用同一规则
+  if("false"){
+    print("unreachable")
+  } else {
+    print("if(\"false\") is false")   // if("false") is false
+  }
+}
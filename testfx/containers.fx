@@ -0,0 +1,28 @@
+// 容器里的字符串：逗号、括号与引号都属于字符串本身
+T>Tag{ label(string) }
+E>Pair{ Two(a, b) }
+
+F>main(){
+  faces := [":(", "ok", ":)"]
+  print(faces, faces.>length, faces[1], faces[2])
+
+  o := {"a":"(", "b":2, "c":"x,y"}
+  print(o, o.b, o.c, o["a"])
+
+  quotes := ["say \"hi\"", "back\\slash"]
+  print(quotes[0], quotes[1], quotes.>length)
+
+  t := Tag{label: "a,b}"}
+  print(t, t.label)
+
+  p := Pair.Two("x,y", "(z")
+  print(p, p.a, p.b)
+  if(p is Pair.Two(a, b)){
+    print("a =", a, "b =", b)
+  }
+
+  L>faces:(i, f){
+    print(i, f)
+  }
+  print([[1, "a,b"], {"k":"]"}][0][1])
+}
@@ -0,0 +1,27 @@
+// 文档注释：紧挨在 F> 前的 `///` 会挂到函数上，`flyux doc testfx/docs.fx` 输出：
+//
+//   # docs.fx
+//
+//   ## `F>area(w(float), h(float))`
+//
+//   Area of a w × h rectangle.
+//
+//   ## `F>main()`
+//
+// 块注释 /* ... */ 可以嵌套，也可以写在一行中间
+
+/// Area of a w × h rectangle.
+F>area(w(float), h(float)){
+  R> w * h
+}
+
+/*
+  外层块注释
+  /* 内层块注释 */
+  仍然是注释：print("hidden")
+*/
+F>main(){
+  print(area(3, /* 行内 */ 4))    // 12.0（w、h 标注为 float）
+  //// 四个斜杠是普通注释
+  print("done")                  // done
+}
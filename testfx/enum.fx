@@ -0,0 +1,45 @@
+// 枚举 / 带载荷的标签联合
+E>Shape{ Circle(r), Rect(w, h), Empty }
+
+F>Shape.area(s){
+  if(s is Shape.Circle(r)){
+    R>3 * r * r
+  }(s is Shape.Rect(w, h)){
+    R>w * h
+  }
+  R>0
+}
+
+F>main(){
+  c := Shape.Circle(2)
+  r := Shape.Rect(3, 4)
+  e := Shape.Empty
+  print(c, r, e)
+  print("typeof:", typeof(c), c.>typeof, "variant:", r.>variant)
+  print("payload:", c.r, r.w, r.h)
+
+  shapes := [c, r, e]
+  L>shapes:s{
+    print("  area of", s, "=", s.>area)
+  }
+
+  // 与 if 分支配合
+  if(e is Shape.Circle(_)){
+    print("circle")
+  } elif(e is Shape.Empty){
+    print("empty")
+  }
+
+  // 类型注解
+  k:(Shape)= Shape.Rect(1, 1)
+  print("k =", k, "same tag:", k.>variant = r.>variant)
+
+  // 没有 eq 方法时按值比较
+  s := Shape.Empty
+  print("s == Shape.Empty:", s == Shape.Empty, "k = r:", k = r, "k != r:", k != r)
+  print("c == Shape.Circle(2):", c == Shape.Circle(2))
+
+  // 拼成枚举样子的字符串不是枚举值
+  fake := "Shape.Circle(2)"
+  print("typeof fake:", typeof(fake), "is Shape.Circle:", fake is Shape.Circle(_))
+}
@@ -0,0 +1,19 @@
+F>one(x){
+  R>x
+}
+
+F>main(){
+  // 宽松模式（默认）下的回退值；用 --strict 或文件头 `//! strict` 运行时，
+  // 每一行都会变成带行列号的运行时错误
+  arr := [1, 2, 3]
+  obj := {"a": 1}
+  print("divide by zero:", 1 / 0)         // divide by zero: 0
+  print("not a number:", "abc" * 2)       // not a number: 0
+  print("unknown fn:", nosuch(1))         // unknown fn: null
+  print("out of bounds:", arr[5])         // out of bounds: null
+  print("missing key:", obj["b"])         // missing key: null
+  print("missing prop:", obj.b)           // missing prop: null
+  print("bad operator:", 1 & 2)           // bad operator: <bad-op>
+  print("extra argument:", one(1, 2))     // extra argument: 1
+  print("still fine:", 6 / 3, 2 = 2)      // still fine: 2 true
+}
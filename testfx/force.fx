@@ -0,0 +1,25 @@
+// =:: 强制转换赋值，可改变变量的声明类型
+F>main(){
+  n := 42
+  n =:: "7.9"
+  print("int <- \"7.9\":", n)
+
+  n(float) =:: n
+  print("as float:", n)
+
+  n(string) =:: n
+  print("as string:", n)
+
+  flag := 0
+  flag(bool) =:: 3
+  print("bool <- 3:", flag)
+
+  ratio := 1
+  ratio(string) =:: "abc"   // 不写类型时 "abc" 无法转换为 int，报 R088
+  print("ratio:", ratio)
+
+  // 可以覆盖常量（--strict 下给出警告）
+  limit:(int)= 10
+  limit =:: 20
+  print("limit:", limit)
+}
@@ -0,0 +1,36 @@
+// for-each：任意表达式、对象键值、字符串字素、带索引
+F>makeArr(a, b, c){
+  R>[a, b, c]
+}
+
+F>main(){
+  L>makeArr(1, 2, 3):x{
+    print("x =", x, typeof(x))
+  }
+
+  fruits := ["apple", "banana", "cherry"]
+  L>fruits:(i, name){
+    print(i, name)
+  }
+
+  L>"🍎🍌👍🏽🇯🇵é":ch{
+    print("char:", ch)
+  }
+
+  scores := {"math": 90, "art": 75}
+  total := 0
+  count := 0
+  L>scores:(subject, score){
+    total = total + score
+    count = count + 1
+  }
+  print("subjects:", count, "total:", total)
+
+  L>[1.5, true, [1, 2]]:v{
+    print(v, typeof(v))
+  }
+
+  L>10..13:(i, n){
+    print("range", i, n)
+  }
+}
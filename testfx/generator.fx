@@ -0,0 +1,71 @@
+// 生成器 Y> 与惰性适配器
+F>naturals(){
+  n := 1
+  L>{
+    Y>n
+    n++
+  }
+}
+
+F>countdown(from){
+  L>from..=1:i{
+    Y>i
+  }
+  print("  (countdown finished)")
+}
+
+F>double(x){
+  R>x * 2
+}
+
+F>isBig(x){
+  R>x > 2
+}
+
+// 生成器在拉取它的一方的线程上逐步执行，里面可以 sleep
+F>ticks(n){
+  L>[n]:i{
+    sleep(5)
+    Y>i
+  }
+}
+
+// 返回的 iter 比创建它的函数活得久
+F>doubledTicks(n){
+  R>ticks(n).>map(double)
+}
+
+F>main(){
+  L>countdown(3):x{
+    print("countdown", x)
+  }
+
+  // 无限序列：只取需要的部分
+  L>naturals():n{
+    if(n > 3){
+      B>
+    }
+    print("natural", n)
+  }
+
+  bigs := naturals().>filter(isBig).>map(double).>take(4)
+  print("typeof:", typeof(bigs))
+  print("collected:", bigs.>collect)
+
+  L>["a", "b", "c"].>enumerate:pair{
+    print("pair", pair)
+  }
+
+  L>naturals().>zip("xyz"):p{
+    print("zip", p)
+  }
+
+  print("big range:", (0..1000000000).>map(double).>take(3).>collect)
+
+  print("ticks:", doubledTicks(3).>collect)    // ticks: [0,2,4]
+
+  // 句柄带内部标记：拼出来的 "<iter#0>" 只是字符串
+  live := naturals()
+  fake := "<iter#0>"
+  print("fake:", typeof(fake), fake.>take(2).>collect, "live:", typeof(live), live.>take(2).>collect)
+}
@@ -0,0 +1,23 @@
+F>main(){
+  // 标识符按 NFC 规范化：预组合的 é 与 e + U+0301 是同一个变量
+  café := 1
+  café = café + 1
+  print("nfc:", café)                    // nfc: 2
+
+  // 变体选择符不区分名字：❤️ 与 ❤ 相同
+  ❤️ := "love"
+  print("variation selector:", ❤)          // variation selector: love
+
+  // ZWJ 序列是一个完整的 emoji 名字，与分开的 emoji 不同
+  👨‍👩‍👧 := "family"
+  👨👩👧 := "three people"
+  print("zwj:", 👨‍👩‍👧, "/", 👨👩👧)   // zwj: family / three people
+  👍🏽 := "thumbs"
+  🇨🇳 := "flag"
+  print("modifier and flag:", 👍🏽, 🇨🇳)    // modifier and flag: thumbs flag
+
+  // 拉丁 a 与西里尔 а 外形相同：运行时在 stderr 给出形近警告
+  total := 10
+  totаl := 20
+  print("confusable:", total, totаl)        // confusable: 10 20
+}
@@ -0,0 +1,43 @@
+// R> 与 B> 写在 if 分支里时，结束的是所在的函数或循环，而不只是这个分支
+F>sign(n){
+  if(n < 0){
+    R>"negative"
+  }(n = 0){
+    R>"zero"
+  }
+  R>"positive"
+}
+
+F>firstOver(limit){
+  L>[10]:i{
+    if(i * i > limit){
+      R>i
+    }
+  }
+  R>null
+}
+
+F>classify(n){
+  if(n > 100){
+    if(n > 1000){ R>"huge" }
+    R>"big"
+  } else {
+    print("small path")
+  }
+  R>"small"
+}
+
+F>main(){
+  print(sign(-3), sign(0), sign(7))      // negative zero positive
+  print(firstOver(20))                   // 5
+  print(classify(5000), classify(500))   // huge big
+  print(classify(1))
+  // small path
+  // small
+  count := 0
+  L>{
+    count++
+    if(count = 3){ B> }
+  }
+  print("count:", count)                 // count: 3
+}
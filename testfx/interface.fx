@@ -0,0 +1,27 @@
+// 接口声明与一致性检查；内置 Sized / Iterable 协议让用户类型可被 length、sort 与 L> 使用
+P>Describable{ describe }
+
+T>Stack: Iterable, Describable{ items(obj) }
+
+F>Stack.length(s){
+  R>s.items.>length
+}
+
+F>Stack.get(s, i){
+  R>s.items[i]
+}
+
+F>Stack.describe(s){
+  R>"Stack"
+}
+
+F>main(){
+  st := Stack{items: [3, 1, 2]}
+  print("length:", st.>length)
+  print("sorted:", st.>sort)
+  print(st.>describe, "of", st.>length)
+
+  L>st:x{
+    print("  item", x)
+  }
+}
@@ -1,26 +1,37 @@
 F>main(){
+  // 每行注释为期望输出；比较与逻辑运算都得到 true / false
+
   // 比较运算符
-  print("1 >  0:",  1 > 0)
-  print("1 <  0:",  1 < 0)
-  print("1 >= 1:",  1 >= 1)
-  print("1 <= 1:",  1 <= 1)
-  print("1 =  1:",  1 = 1)   // 单等同“==”
-  print("1 == 1:", 1 == 1)
+  print("1 >  0:",  1 > 0)          // 1 >  0: true
+  print("1 <  0:",  1 < 0)          // 1 <  0: false
+  print("1 >= 1:",  1 >= 1)         // 1 >= 1: true
+  print("1 <= 1:",  1 <= 1)         // 1 <= 1: true
+  print("1 =  1:",  1 = 1)   // 单等同“==”  -> 1 =  1: true
+  print("1 == 1:", 1 == 1)          // 1 == 1: true
 
   // 逻辑运算符
-  print("true && true:",  1 && 1)   // 非零视为 true
-  print("true && false:", 1 && 0)
-  print("false && true:", 0 && 1)
-  print("false && false:",0 && 0)
+  print("true && true:",  1 && 1)   // 非零视为 true -> true && true: true
+  print("true && false:", 1 && 0)   // true && false: false
+  print("false && true:", 0 && 1)   // false && true: false
+  print("false && false:",0 && 0)   // false && false: false
 
-  print("true || true:",  1 || 1)
-  print("true || false:", 1 || 0)
-  print("false || true:", 0 || 1)
-  print("false || false:",0 || 0)
+  print("true || true:",  1 || 1)   // true || true: true
+  print("true || false:", 1 || 0)   // true || false: true
+  print("false || true:", 0 || 1)   // false || true: true
+  print("false || false:",0 || 0)   // false || false: false
 
   // 复合表达式
-  print(" (1<2) && (2<3):", (1<2) && (2<3))
-  print(" (1<2) && (2>3):", (1<2) && (2>3))
-  print(" (1>2) || (2<3):", (1>2) || (2<3))
-  print(" (1>2) || (2>3):", (1>2) || (2>3))
-}
\ No newline at end of file
+  print(" (1<2) && (2<3):", (1<2) && (2<3))   //  (1<2) && (2<3): true
+  print(" (1<2) && (2>3):", (1<2) && (2>3))   //  (1<2) && (2>3): false
+  print(" (1>2) || (2<3):", (1>2) || (2<3))   //  (1>2) || (2<3): true
+  print(" (1>2) || (2>3):", (1>2) || (2>3))   //  (1>2) || (2>3): false
+
+  // 链式比较与布尔值混用
+  print("1 < 2 < 3:", 1 < 2 < 3)              // 1 < 2 < 3: true
+  print("3 > 2 > 2:", 3 > 2 > 2)              // 3 > 2 > 2: false
+  print("(1 < 2 < 3) && 1:", (1 < 2 < 3) && 1) // (1 < 2 < 3) && 1: true
+  print("true && \"yes\":", true && "yes")     // true && "yes": true
+
+  // 布尔值参与算术时为 1 / 0
+  print("true + true:", true + true)          // true + true: 2
+}
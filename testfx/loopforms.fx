@@ -0,0 +1,61 @@
+// 无限循环、do-while、until 与 B> / R>
+F>firstOver(limit){
+  n := 1
+  L>{
+    if(n * n > limit){
+      R>n
+    }
+    n++
+  }
+}
+
+F>helper(){
+  R>"ignored"
+}
+
+F>main(){
+  i := 0
+  L>{
+    i++
+    if(i == 3){
+      B>
+    }
+  }
+  print("infinite stopped at", i)
+
+  // 条件一开始就不成立，循环体仍执行一次
+  j := 10
+  L>{
+    print("do-while ran with j =", j)
+    j++
+  }:(j < 5)
+
+  k := 0
+  L>!(k == 4){
+    k++
+  }
+  print("until k =", k)
+
+  m := 0
+  L>{
+    m = m + 2
+  }:!(m > 5)
+  print("do-until m =", m)
+
+  print("first square over 50:", firstOver(50))
+
+  L>[5]:t{
+    if(t == 2){
+      B>
+    }
+    print("times", t)
+  }
+
+  // 语句形式的调用丢弃返回值，不会结束循环或 main
+  L>[2]:r{
+    helper()
+    print("after helper", r)
+  }
+  helper()
+  print("statement calls do not return from main")
+}
@@ -0,0 +1,15 @@
+//! strict
+// 诊断都带稳定编号：L 词法、P 语法、R 运行时、W 警告、E 命令行。
+// 默认英文；FLYUX_LANG=zh 或 --lang zh 时同一编号显示中文（--lang zh 也开启
+// 中文关键字，FLYUX_LANG 只影响诊断）：
+//   warning[W001]: '=::' 覆盖了常量 'limit'
+//   error[R093]: 运行时错误（第 14 行，第 18 列）：对象中没有键 'c'
+F>main(){
+  limit :(int)= 3
+  limit =:: 5                 // warning[W001]: '=::' overrides constant 'limit'
+  print("limit:", limit)      // limit: 5
+  obj := {"a": 1, "b": 2}
+  print("a:", obj["a"])       // a: 1
+  // error[R093]: Runtime error at line 14, col 18: Key 'c' not found in object
+  print("c:", obj["c"])
+}
@@ -0,0 +1,35 @@
+// 类型方法与 .> 分派：内置 → 类型方法 → 普通函数
+T>Point{ x(float), y(float) }
+
+F>Point.norm(p){
+  R>(p.x * p.x) + (p.y * p.y)
+}
+
+F>Point.shift(p, d){
+  R>Point{x: p.x + d, y: p.y + d}
+}
+
+F>obj.norm(arr){
+  R>arr.>length
+}
+
+F>norm(n){
+  R>n * n
+}
+
+F>main(){
+  p := Point{x: 3, y: 4}
+  print("p.>norm =", p.>norm)
+  print("p.>shift(1).>norm =", p.>shift(1).>norm)
+
+  arr := [1, 2, 3]
+  print("arr.>norm =", arr.>norm)
+  n := 5
+  print("n.>norm =", n.>norm)
+
+  // 同一调用点对不同接收者类型重新分派
+  vals := [p, arr, 2]
+  L>vals:v{
+    print("  v.>norm =", v.>norm)
+  }
+}
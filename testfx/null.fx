@@ -0,0 +1,54 @@
+F>nothing(){
+  x := 1
+}
+
+F>shadowNull(){
+  null := 5
+}
+
+F>shadowTrue(){
+  true => 0
+}
+
+F>double(n){
+  R>n * 2
+}
+
+F>main(){
+  // 每行注释为期望输出
+  a := null
+  print("a:", a)                          // a: null
+  print("typeof:", typeof(a))             // typeof: null
+  print("no return:", nothing())          // no return: null
+  print("falsy:", !a)                     // falsy: true
+
+  user := {"name": "Ada", "address": {"city": "London"}}
+  print("city:", user.address.city)       // city: London
+  print("missing key:", user.phone)       // missing key: null
+  print("safe access:", user.phone?.area) // safe access: null
+  print("safe chain:", a?.b?.c)           // safe chain: null
+
+  arr := [1, 2, 3]
+  print("out of bounds:", arr[10])        // out of bounds: null
+
+  n := 21
+  print("safe pipe:", n?.>double)         // safe pipe: 42
+  print("safe pipe null:", a?.>double)    // safe pipe null: null
+
+  print("coalesce:", a ?? "default")      // coalesce: default
+  print("coalesce set:", n ?? 0)          // coalesce set: 21
+  print("chain:", user.phone ?? a ?? 7)   // chain: 7
+  print("zero kept:", 0 ?? 5)             // zero kept: 0
+
+  // 字符串 "null" 只是文本，不是缺失值
+  s := "null"
+  print("string null:", s ?? "fallback", typeof(s), s = null)  // string null: null string false
+  print("in array:", [null, "null"][1] ?? "gone")              // in array: null
+
+  // null、true、false 不能被声明成变量
+  print("declare:", try(shadowNull).error, try(shadowTrue).error)  // declare: R098 R098
+
+  // && 优先于 ||
+  print("1 || 0 && 0:", 1 || 0 && 0)      // 1 || 0 && 0: true
+  print("0 && 1 || 1:", 0 && 1 || 1)      // 0 && 1 || 1: true
+}
@@ -0,0 +1,17 @@
+// 数字字面量：0x/0o/0b 整数、_ 分隔符、指数、.5
+// 写错的字面量（1.2.3、0b102、3px）是带行列号的词法错误
+F>main(){
+  print(0xFF, 0o17, 0b1010_1010)        // 255 15 170
+  print(1_000_000, -0x10)               // 1000000 -16
+  print(1e-9, 2.5E3, 6.02e+2)           // 0.000000001 2500.0 602.0
+  print(.5, .25 + .5, [.5, 1][0])       // 0.5 0.75 0.5
+  print(0..3, 2.5.>typeof, 1_0.>typeof)  // 0..3 float int
+  print(0x1e-5)                         // 25
+  print(0XFF, 1.0.>typeof, 2.5e3.>typeof) // 255 float float（浮点数总带小数部分）
+  print(-9223372036854775808)           // -9223372036854775808
+
+  // float 经过运算与转换仍是 float；int 与 int 的运算留在 int
+  d(float) := 3
+  print(d, typeof(d), 2.5 * 2, 1.5 + 1.5)  // 3.0 float 5.0 3.0
+  print(6 / 3, 7 / 2, 9223372036854775807 + 1)  // 2 3.5 9223372036854776000.0
+}
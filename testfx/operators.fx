@@ -0,0 +1,23 @@
+// 运算符按最长匹配切成独立的 token（--token 可以看到 Le、AndAnd、PlusPlus…），
+// 所以 `1 < = 2` 与 `1 = = 1` 是语法错误，`@`、`#`、单独的 `?` 这类无法识别的字符是词法错误
+F>main(){
+  a := 3
+  b := 5
+  print(a <= b, a >= b, a == 3, a = 3)     // true false true true
+  print(a < b && b > a, a > b || b < a)    // true false
+  print(1 < a <= 3 < b)                    // true
+  print(a + b * 2 - 1 / 2)                 // 7.5（算术从左到右）
+  n := 0
+  n++
+  ++n
+  n--
+  print(n)                                 // 1
+  print(5--3)                              // 8（数字之后的 -- 是两个减号）
+  missing := null
+  print(missing ?? "fallback", missing?.x, missing?.>length)  // fallback null null
+  L>(i := 0; i < 2; i++){
+    print("i =", i, !(i = 1))
+  }
+  // i = 0 true
+  // i = 1 false
+}
@@ -0,0 +1,36 @@
+// 运算符重载：左操作数的类型方法 add / sub / mul / eq / lt
+T>Money{ cents(int) }
+
+F>Money.add(a, b){
+  R>Money{cents: a.cents + b.cents}
+}
+F>Money.sub(a, b){
+  R>Money{cents: a.cents - b.cents}
+}
+F>Money.mul(a, k){
+  R>Money{cents: a.cents * k}
+}
+F>Money.eq(a, b){
+  R>a.cents = b.cents
+}
+F>Money.lt(a, b){
+  R>a.cents < b.cents
+}
+
+F>main(){
+  a := Money{cents: 150}
+  b := Money{cents: 250}
+  c := Money{cents: 400}
+  print("a + b =", a + b)
+  print("c - a =", c - a)
+  print("a * 3 =", a * 3)
+  print("a + b == c:", a + b == c, "a != b:", a != b, "a + b != c:", a + b != c)
+  print("a < b:", a < b, "b > a:", b > a, "c <= c:", c <= c, "a >= b:", a >= b)
+
+  // 链式比较同样走重载
+  print("a < b < c:", a < b < c)
+  print("c > b > a:", c > b > a)
+  if(a < b <= c){
+    print("ordered")
+  }
+}
@@ -0,0 +1,46 @@
+// 区间字面量与步进遍历（惰性，不生成数组）
+F>zeroStep(n){
+  R>1..10:n
+}
+
+F>main(){
+  r := 1..5
+  print("r =", r, "length:", r.>length, "r[2] =", r[2], "typeof:", typeof(r))
+
+  print("1..=10:3:")
+  L>1..=10:3:i{
+    print("  ", i)
+  }
+
+  print("descending 5..0:")
+  L>5..0:i{
+    print("  ", i)
+  }
+
+  n := 3
+  print("0..n:")
+  L>0..n:i{
+    print("  ", i)
+  }
+
+  // 具名计数器，嵌套时互不覆盖
+  L>[2]:row{
+    L>[3]:col{
+      print("  cell", row, col)
+    }
+  }
+
+  big := 0..1000000000
+  print("big length:", big.>length, "last:", big[999999999])
+
+  // 跨越 i64 一半以上的区间长度也不会溢出
+  wide := -9000000000000000000..9000000000000000000
+  print("wide length:", wide.>length)   // wide length: 18000000000000000000
+
+  // 写成字符串的 "1..5" 只是文本，不是区间
+  text := "1..5"
+  print("text:", text.>typeof, text.>length, (1..5).>typeof)   // text: string 4 range
+
+  // 步长为 0 是带位置的运行时错误
+  print("zero step:", try(zeroStep, 0).error)   // zero step: R024
+}
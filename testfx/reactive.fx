@@ -0,0 +1,47 @@
+// 响应式绑定：=> 在依赖被赋值后重新计算，<=> 为双向绑定。
+// 绑定属于声明它的函数调用，其他函数里的同名变量不会影响它
+F>scaled(k){
+  base := 2
+  out => base * k
+  base = 5
+  R>out
+}
+
+F>shadow(){
+  qty := 100
+  qty = 200
+  R>qty
+}
+
+F>main(){
+  price := 20
+  qty := 3
+  total => price * qty
+  label => total + 1
+  print("total:", total, "label:", label)
+
+  qty = 5
+  print("after qty = 5:", total, label)
+
+  price++
+  print("after price++:", total, label)
+
+  // 双向绑定：读写都作用于 qty
+  count <=> qty
+  count = 10
+  print("count = 10 → qty:", qty, "total:", total)
+  qty = 2
+  print("qty = 2 → count:", count)
+
+  // 对象字段赋值同样会触发重算
+  cart := {n: 4}
+  items => cart.n * 2
+  cart.n = 7
+  print("items:", items)
+
+  print("per call:", scaled(3), scaled(4))     // per call: 15 20
+  shadow()
+  print("after shadow():", total)              // after shadow(): 42
+  fake := "<bind#0>"
+  print("plain string:", fake)                 // plain string: <bind#0>
+}
@@ -0,0 +1,35 @@
+// 具名记录类型
+T>Point{ x(float), y(float) }
+T>Line{ from(Point), to(Point), label }
+
+// 拼成记录样子的字符串不是记录
+F>forged(){
+  q:(Point)= "Point{}"
+  R>q
+}
+
+F>main(){
+  p := Point{x: 1, y: 2}
+  print("p =", p)
+  print("p.x =", p.x, "p.y =", p.y)
+
+  // 字段赋值会做类型检查
+  p.x = 3.5
+  print("p.x after =", p.x)
+
+  // 类型注解
+  q:(Point)= Point{y: 4, x: 0}
+  print("q =", q)
+
+  // 嵌套记录
+  l := Line{from: p, to: q, label: "diag"}
+  print("l.to.y =", l.to.y)
+  l.from.y = 10
+  print("l.from =", l.from)
+
+  // 没有 eq 方法的记录按字段值比较
+  fake := "Point{\"x\":1}"
+  print("typeof fake:", typeof(fake), "forged:", try(forged).error)
+
+  print("q == Point{x: 0, y: 4}:", q == Point{x: 0, y: 4}, "p = q:", p = q, "p != q:", p != q)
+}
@@ -0,0 +1,71 @@
+// 尾调用不占调用深度；普通递归受 --max-depth 限制（默认 10000），超出时报 R038，可用 try 接住
+F>countdown(n, acc){
+  if(n == 0){
+    R>acc
+  }
+  R>countdown(n - 1, acc + 1)
+}
+
+F>isEven(n){
+  if(n == 0){
+    R>1
+  }
+  R>isOdd(n - 1)
+}
+
+F>isOdd(n){
+  if(n == 0){
+    R>0
+  }
+  R>isEven(n - 1)
+}
+
+F>depth(n){
+  if(n == 0){
+    R>0
+  }
+  R>1 + depth(n - 1)
+}
+
+F>forever(n){
+  R>1 + forever(n + 1)
+}
+
+// 尾调用也会求值每个实参，并检查标注了类型的形参
+F>note(n){
+  print("evaluated", n)
+  R>n
+}
+
+F>tailExtra(n){
+  if(n == 0){
+    R>"done"
+  }
+  R>tailExtra(n - 1, note(n))
+}
+
+F>typedCount(n(int)){
+  if(n == 0){
+    R>"typed done"
+  }
+  R>typedCount(n - 1)
+}
+
+F>badTail(){
+  R>typedCount("abc")
+}
+
+F>main(){
+  print("tail calls:", countdown(50000, 0))
+  print("mutual tail calls:", isEven(20001))
+  print("deep recursion:", depth(3000))
+
+  // 栈溢出是普通的运行时错误，try 可以接住
+  r := try(forever, 0)
+  print("overflow:", r.error)              // overflow: R038
+  print("after overflow:", depth(10))      // after overflow: 10
+  print(try(depth, 5))                     // {"value":5,"error":null}
+
+  print(tailExtra(2))                      // evaluated 2, evaluated 1, done
+  print(typedCount(3), try(badTail).error) // typed done R040
+}
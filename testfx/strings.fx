@@ -0,0 +1,17 @@
+// 字符串字面量：\u{...} 与 \x.. 转义、r"..." 原样字符串、"""...""" 多行字符串。
+// 三引号字符串去掉公共缩进（以结束引号所在行为准）；未闭合的字符串与无效转义
+// 都是指向开头引号的词法错误
+F>main(){
+  print("rocket \u{1F680}, \x46\x58")   // rocket 🚀, FX
+  print(r"C:\new\table")                // C:\new\table
+  poem := """
+    Roses are red,
+      violets are "blue".
+    """
+  print(poem)
+  // Roses are red,
+  //   violets are "blue".
+  print(poem.>length)                   // 36
+  print(r"""raw "quotes" \n""")         // raw "quotes" \n
+  print("tab[\t] quote[\"]")            // tab[	] quote["]
+}
@@ -0,0 +1,28 @@
+// 任务的错误留在任务上，在 await 处重新抛出
+F>broken(){
+  sleep(5)
+  n := null
+  R>n.x
+}
+
+F>slow(){
+  sleep(20)
+  R>"slow done"
+}
+
+F>waitFor(t){
+  R>await(t)
+}
+
+F>main(){
+  b := spawn(broken)
+  // slow 等待期间 broken 失败，这个 try 接不到它的错误
+  r := try(slow)
+  print("slow:", r.value, r.error)
+  print("broken:", try(waitFor, b).error)
+  print("again:", try(waitFor, b).error)
+
+  // 没人 await 的任务错误在所有任务结束后报告
+  spawn(broken)
+  print("main done")
+}
@@ -0,0 +1,61 @@
+// 系统线程、通道与并行遍历
+F>sumTo(n){
+  total := 0
+  L>1..=n:i{
+    total = total + i
+  }
+  R>total
+}
+
+F>producer(ch, count){
+  L>[count]:i{
+    send(ch, i * i)
+  }
+  close(ch)
+}
+
+F>recvFrom(ch){
+  R>recv(ch)
+}
+
+F>joinText(){
+  R>join("<thread#1>")
+}
+
+F>recvNothing(){
+  R>recv()
+}
+
+F>collectNothing(){
+  R>collect()
+}
+
+F>main(){
+  a := thread(sumTo, 1000)
+  b := spawn_thread(sumTo, 2000)   // thread 的别名（spawn 是协程）
+  print("sums:", join(a), join(b))
+
+  ch := chan()
+  p := thread(producer, ch, 4)
+  L>ch:v{
+    print("received", v)
+  }
+  join(p)
+  print("after close:", recv(ch))
+
+  // 并行遍历：输出按元素顺序
+  L>||[5, 3, 8, 1]:(i, n){
+    print("item", i, "sum", sumTo(n))
+  }
+
+  results := chan()
+  L>||1..=4:n{
+    send(results, n * 10)
+  }
+  close(results)
+  print("collected", results.>collect.>length, "results")
+
+  // 句柄的文字只是字符串；缺参数的内置函数报 R103
+  print(try(recvFrom, "<chan#1>").error, try(joinText).error)   // R009 R019
+  print(try(recvNothing).error, try(collectNothing).error)      // R103 R103
+}
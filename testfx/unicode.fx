@@ -0,0 +1,30 @@
+F>main(){
+  // 每行注释为期望输出；长度、下标与切片都按扩展字素簇计算
+  apple := "🍎"
+  print("length:", apple.>length)               // length: 1
+  print("chars:", apple.>chars)                 // chars: 1
+  print("bytes:", apple.>bytes)                 // bytes: 4
+
+  family := "👨‍👩‍👧"
+  print("zwj family:", family.>length, family.>chars)   // zwj family: 1 5
+  flags := "🇨🇳🇯🇵"
+  print("flags:", flags.>length)                // flags: 2
+  cafe := "café"  // e + U+0301
+  print("combining:", cafe.>length, cafe.>chars)        // combining: 4 5
+  print("hangul:", "한국어".>length)            // hangul: 3
+  print("crlf:", "a\r\nb".>length)              // crlf: 3
+
+  fruit := "🍎🍌🍇🍉"
+  print("index:", fruit[1])                     // index: 🍌
+  print("slice:", fruit[1:3])                   // slice: 🍌🍇
+  print("from:", fruit[2:])                     // from: 🍇🍉
+  print("to:", fruit[:1])                       // to: 🍎
+  print("clamped:", fruit[3:10])                // clamped: 🍉
+  print("empty:", fruit[3:1].>length)           // empty: 0
+  print("cjk:", "你好世界"[1:3])                // cjk: 好世
+  print("last combining:", cafe[3])             // last combining: é
+
+  arr := [1, 2, 3, 4]
+  print("array slice:", arr[1:3])               // array slice: [2,3]
+  print("out of bounds:", fruit[9])             // out of bounds: null
+}
@@ -0,0 +1,39 @@
+//! lang zh
+// 中文关键字：与英文拼写得到相同的 Token 与语法树
+
+函数>阶乘(数){
+  如果(数 <= 1){
+    返回>1
+  }
+  返回>数 * 阶乘(数 - 1)
+}
+
+函数>分类(分数){
+  如果(分数 >= 90){
+    返回>"优秀"
+  }否则如果(分数 >= 60){
+    返回>"及格"
+  }否则{
+    返回>"不及格"
+  }
+}
+
+函数>main(){
+  print("5! =", 阶乘(5))                  // 5! = 120
+  print(分类(95), 分类(70), 分类(30))     // 优秀 及格 不及格
+
+  总和 := 0
+  循环>[4]:次{
+    总和 = 总和 + 次
+  }
+  print("总和:", 总和)                    // 总和: 6
+
+  循环>{
+    总和 = 总和 - 1
+    如果(总和 < 3){ 跳出> }
+  }
+  print("跳出后:", 总和)                  // 跳出后: 2
+
+  开关 := 真
+  print("真假空:", 开关, !开关, 空 ?? "默认")   // 真假空: true false 默认
+}